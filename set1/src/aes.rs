@@ -0,0 +1,962 @@
+//! AES block cipher (FIPS-197)
+
+use crate::{aes_hw, modes::BlockCipher};
+
+/// Size of an AES block in bytes. It is the same for every key size.
+pub const BLOCK_SIZE: usize = 16;
+
+/// The AES substitution box. Every byte of the state is replaced by its entry
+/// in this table during the SubBytes step.
+pub(crate) const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// The inverse of `SBOX`, used by the InvSubBytes step during decryption.
+pub(crate) const INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+/// Round constants used by the key schedule. Only the first byte of each
+/// round constant word is non-zero, so only that byte is stored.
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// The implementations that can run the block function. They all give the
+/// same results and share one key schedule layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Lookup tables for the S-box. Easy to follow, but the table index
+    /// depends on the key and the data, which leaks through the cache timing.
+    Table,
+    /// The S-box is computed as an inversion in GF(2^8) followed by the
+    /// affine map, without table lookups or branches on secret data.
+    ConstantTime,
+    /// AES-NI on x86-64 or the ARMv8 cryptography extension.
+    Hardware,
+}
+
+impl Backend {
+    /// Returns the hardware backend when the CPU supports it, and the
+    /// constant-time software backend otherwise.
+    pub fn detect() -> Backend {
+        if Backend::Hardware.is_available() {
+            Backend::Hardware
+        } else {
+            Backend::ConstantTime
+        }
+    }
+
+    /// Checks at runtime whether the backend can be used on this CPU.
+    pub fn is_available(self) -> bool {
+        match self {
+            Backend::Table | Backend::ConstantTime => true,
+            Backend::Hardware => aes_hw::is_supported(),
+        }
+    }
+}
+
+/// The state of one AES round, as produced by `Aes::encrypt_block_traced`.
+/// Round 0 is the initial AddRoundKey, so only `start`, `round_key` and `end`
+/// are meaningful there; the last round has no MixColumns step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundTrace {
+    pub round: usize,
+    pub start: [u8; BLOCK_SIZE],
+    pub after_sub_bytes: Option<[u8; BLOCK_SIZE]>,
+    pub after_shift_rows: Option<[u8; BLOCK_SIZE]>,
+    pub after_mix_columns: Option<[u8; BLOCK_SIZE]>,
+    pub round_key: [u8; BLOCK_SIZE],
+    pub end: [u8; BLOCK_SIZE],
+}
+
+/// An AES key schedule, ready to encrypt and decrypt single blocks. The key
+/// size picks the variant: 16 bytes for AES-128, 24 bytes for AES-192 and 32
+/// bytes for AES-256.
+///
+/// The state is kept in the FIPS-197 byte order, i.e. byte `i` of a block is
+/// row `i % 4` and column `i / 4` of the state matrix.
+///
+/// # Examples
+/// ```
+/// use set1::aes::Aes;
+///
+/// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+/// let mut block = *b"sixteen byte msg";
+///
+/// aes.encrypt_block(&mut block);
+/// assert_ne!(&block, b"sixteen byte msg");
+///
+/// aes.decrypt_block(&mut block);
+/// assert_eq!(&block, b"sixteen byte msg");
+/// ```
+///
+/// Code that handles real keys should pick a backend that does not leak
+/// through timing:
+/// ```
+/// use set1::aes::{Aes, Backend};
+///
+/// let aes = Aes::with_backend(b"YELLOW SUBMARINE", Backend::detect()).unwrap();
+/// assert_ne!(aes.backend(), Backend::Table);
+/// ```
+#[derive(Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; BLOCK_SIZE]>,
+    /// Round keys of the equivalent inverse cipher, for the hardware backend
+    /// only: the middle ones go through InvMixColumns.
+    decryption_keys: Vec<[u8; BLOCK_SIZE]>,
+    backend: Backend,
+}
+
+impl Aes {
+    /// Expands `key` into the round keys, using the table backend. Returns an
+    /// error if the key is not 16, 24 or 32 bytes long.
+    pub fn new(key: &[u8]) -> Result<Self, &'static str> {
+        Aes::with_backend(key, Backend::Table)
+    }
+
+    /// Expands `key` into the round keys for the given backend. Returns an
+    /// error if the key has the wrong size or the backend is not supported by
+    /// this CPU. That check is the only one: the hardware backend relies on it
+    /// for every block.
+    pub fn with_backend(key: &[u8], backend: Backend) -> Result<Self, &'static str> {
+        if !backend.is_available() {
+            return Err("AES instructions are not available on this CPU");
+        }
+
+        // only the table backend may look up secret bytes in a table.
+        let sub_byte = match backend {
+            Backend::Table => table_sub_byte,
+            Backend::ConstantTime | Backend::Hardware => ct_sub_byte,
+        };
+
+        let round_keys = expand_key_with(key, sub_byte)?;
+        let mut decryption_keys = Vec::new();
+        if backend == Backend::Hardware {
+            let rounds = round_keys.len() - 1;
+            decryption_keys = round_keys.clone();
+            for round_key in &mut decryption_keys[1..rounds] {
+                inv_mix_columns(round_key);
+            }
+        }
+
+        Ok(Aes {
+            round_keys,
+            decryption_keys,
+            backend,
+        })
+    }
+
+    /// Returns the backend doing the work.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Returns the number of rounds: 10, 12 or 14 depending on the key size.
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    /// Returns the expanded key, one round key per round plus the initial one.
+    pub fn round_keys(&self) -> &[[u8; BLOCK_SIZE]] {
+        &self.round_keys
+    }
+
+    /// Encrypts a single block in place like `encrypt_block`, and records the
+    /// state after every step in the layout of FIPS-197 appendix C. The
+    /// trace always comes from the table round functions, whatever the
+    /// backend, so it is meant for study and not for secret data.
+    ///
+    /// # Examples
+    /// ```
+    /// use set1::aes::Aes;
+    ///
+    /// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+    /// let mut block = *b"sixteen byte msg";
+    /// let trace = aes.encrypt_block_traced(&mut block);
+    ///
+    /// // the initial AddRoundKey, then one entry per round.
+    /// assert_eq!(trace.len(), 11);
+    /// assert_eq!(&trace[0].start, b"sixteen byte msg");
+    /// assert_eq!(trace[10].end, block);
+    /// assert!(trace[10].after_mix_columns.is_none());
+    /// ```
+    pub fn encrypt_block_traced(&self, block: &mut [u8; BLOCK_SIZE]) -> Vec<RoundTrace> {
+        let rounds = self.rounds();
+        let mut trace = Vec::with_capacity(rounds + 1);
+
+        let start = *block;
+        add_round_key(block, &self.round_keys[0]);
+        trace.push(RoundTrace {
+            round: 0,
+            start,
+            after_sub_bytes: None,
+            after_shift_rows: None,
+            after_mix_columns: None,
+            round_key: self.round_keys[0],
+            end: *block,
+        });
+
+        for round in 1..=rounds {
+            let start = *block;
+            sub_bytes(block, table_sub_byte);
+            let after_sub_bytes = *block;
+            shift_rows(block);
+            let after_shift_rows = *block;
+            let after_mix_columns = (round < rounds).then(|| {
+                mix_columns(block);
+                *block
+            });
+            add_round_key(block, &self.round_keys[round]);
+
+            trace.push(RoundTrace {
+                round,
+                start,
+                after_sub_bytes: Some(after_sub_bytes),
+                after_shift_rows: Some(after_shift_rows),
+                after_mix_columns,
+                round_key: self.round_keys[round],
+                end: *block,
+            });
+        }
+
+        trace
+    }
+
+    /// Encrypts a single block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        match self.backend {
+            Backend::Table => self.encrypt_software(block, table_sub_byte),
+            Backend::ConstantTime => self.encrypt_software(block, ct_sub_byte),
+            // SAFETY: `with_backend` checked that the CPU has the instructions.
+            Backend::Hardware => unsafe { aes_hw::encrypt_block(&self.round_keys, block) },
+        }
+    }
+
+    /// Decrypts a single block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        match self.backend {
+            Backend::Table => self.decrypt_software(block, table_inv_sub_byte),
+            Backend::ConstantTime => self.decrypt_software(block, ct_inv_sub_byte),
+            // SAFETY: `with_backend` checked that the CPU has the instructions.
+            Backend::Hardware => unsafe { aes_hw::decrypt_block(&self.decryption_keys, block) },
+        }
+    }
+
+    /// The FIPS-197 cipher, with the S-box given by `sub_byte`.
+    fn encrypt_software(&self, block: &mut [u8; BLOCK_SIZE], sub_byte: fn(u8) -> u8) {
+        let rounds = self.rounds();
+
+        add_round_key(block, &self.round_keys[0]);
+        for round_key in &self.round_keys[1..rounds] {
+            sub_bytes(block, sub_byte);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, round_key);
+        }
+
+        // the final round has no MixColumns step.
+        sub_bytes(block, sub_byte);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[rounds]);
+    }
+
+    /// The FIPS-197 inverse cipher, with the inverse S-box given by
+    /// `inv_sub_byte`.
+    fn decrypt_software(&self, block: &mut [u8; BLOCK_SIZE], inv_sub_byte: fn(u8) -> u8) {
+        let rounds = self.rounds();
+
+        add_round_key(block, &self.round_keys[rounds]);
+        inv_shift_rows(block);
+        sub_bytes(block, inv_sub_byte);
+        for round_key in self.round_keys[1..rounds].iter().rev() {
+            add_round_key(block, round_key);
+            inv_mix_columns(block);
+            inv_shift_rows(block);
+            sub_bytes(block, inv_sub_byte);
+        }
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().expect("AES blocks are 16 bytes");
+        Aes::encrypt_block(self, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().expect("AES blocks are 16 bytes");
+        Aes::decrypt_block(self, block);
+    }
+}
+
+/// AES with a configurable number of rounds, for cryptanalysis exercises. The
+/// round keys are the first `rounds + 1` of the normal key schedule, and the
+/// last round may keep its MixColumns step, which makes the cipher easier to
+/// reason about for some attacks. With the full number of rounds and no final
+/// MixColumns it is plain AES.
+///
+/// # Examples
+/// ```
+/// use set1::aes::ReducedAes;
+///
+/// let aes = ReducedAes::new(b"YELLOW SUBMARINE", 4, false).unwrap();
+/// let mut block = *b"sixteen byte msg";
+///
+/// aes.encrypt_block(&mut block);
+/// aes.decrypt_block(&mut block);
+/// assert_eq!(&block, b"sixteen byte msg");
+/// ```
+#[derive(Clone)]
+pub struct ReducedAes {
+    round_keys: Vec<[u8; BLOCK_SIZE]>,
+    final_mix_columns: bool,
+}
+
+impl ReducedAes {
+    /// Expands `key` and keeps the round keys for `rounds` rounds. Returns an
+    /// error if the key has the wrong size or `rounds` is zero or more than
+    /// the full number of rounds for the key size.
+    pub fn new(key: &[u8], rounds: usize, final_mix_columns: bool) -> Result<Self, &'static str> {
+        let mut round_keys = expand_key(key)?;
+        if rounds == 0 || rounds >= round_keys.len() {
+            return Err(
+                "Number of rounds should be between 1 and the full number for the key size",
+            );
+        }
+        round_keys.truncate(rounds + 1);
+
+        Ok(ReducedAes {
+            round_keys,
+            final_mix_columns,
+        })
+    }
+
+    /// Returns the number of rounds.
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    /// Returns the round keys in use, starting with the whitening key.
+    pub fn round_keys(&self) -> &[[u8; BLOCK_SIZE]] {
+        &self.round_keys
+    }
+
+    /// Tells whether the last round keeps its MixColumns step.
+    pub fn final_mix_columns(&self) -> bool {
+        self.final_mix_columns
+    }
+
+    /// Encrypts a single block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let rounds = self.rounds();
+
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..=rounds {
+            sub_bytes(block, table_sub_byte);
+            shift_rows(block);
+            if round < rounds || self.final_mix_columns {
+                mix_columns(block);
+            }
+            add_round_key(block, &self.round_keys[round]);
+        }
+    }
+
+    /// Decrypts a single block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let rounds = self.rounds();
+
+        for round in (1..=rounds).rev() {
+            add_round_key(block, &self.round_keys[round]);
+            if round < rounds || self.final_mix_columns {
+                inv_mix_columns(block);
+            }
+            inv_shift_rows(block);
+            sub_bytes(block, table_inv_sub_byte);
+        }
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+impl BlockCipher for ReducedAes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().expect("AES blocks are 16 bytes");
+        ReducedAes::encrypt_block(self, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().expect("AES blocks are 16 bytes");
+        ReducedAes::decrypt_block(self, block);
+    }
+}
+
+/// Runs the AES key expansion and returns the `rounds + 1` round keys. The key
+/// is split into 4 byte words and every following word is the XOR of the word
+/// `key_words` positions back and the previous word, which is first rotated,
+/// substituted and XORed with a round constant at the start of every key
+/// length (AES-256 also substitutes it in the middle). The words are then
+/// grouped into 16 byte round keys.
+///
+/// # Examples
+/// ```
+/// use set1::aes::expand_key;
+///
+/// let round_keys = expand_key(b"YELLOW SUBMARINE").unwrap();
+/// assert_eq!(round_keys.len(), 11);
+/// assert_eq!(&round_keys[0], b"YELLOW SUBMARINE");
+/// ```
+pub fn expand_key(key: &[u8]) -> Result<Vec<[u8; BLOCK_SIZE]>, &'static str> {
+    expand_key_with(key, table_sub_byte)
+}
+
+/// Runs the key expansion with the S-box given by `sub_byte`, so the
+/// constant-time backends don't use the table for the key either.
+fn expand_key_with(
+    key: &[u8],
+    sub_byte: fn(u8) -> u8,
+) -> Result<Vec<[u8; BLOCK_SIZE]>, &'static str> {
+    let key_words = key_words(key.len())?;
+    let total_words = 4 * (key_words + 7);
+
+    let mut words: Vec<[u8; 4]> = key
+        .chunks_exact(4)
+        .map(|word| [word[0], word[1], word[2], word[3]])
+        .collect();
+
+    for i in key_words..total_words {
+        let temp = schedule_core(words[i - 1], i, key_words, sub_byte);
+        words.push(xor_words(words[i - key_words], temp));
+    }
+
+    Ok(words_to_round_keys(&words))
+}
+
+/// Recovers the master key from any part of the expanded key. The key
+/// schedule is a bijection, so `key_len` consecutive bytes of the expanded key
+/// are enough to run it backwards: for AES-128 that is any single round key,
+/// for AES-192 a round key and the first half of the next one, and for
+/// AES-256 two consecutive round keys. `round` is the index of the round key
+/// `expanded` starts with.
+///
+/// # Examples
+/// ```
+/// use set1::aes::{expand_key, invert_key_schedule};
+///
+/// let round_keys = expand_key(b"YELLOW SUBMARINE").unwrap();
+/// let master_key = invert_key_schedule(&round_keys[10], 10).unwrap();
+///
+/// assert_eq!(master_key, b"YELLOW SUBMARINE");
+/// ```
+pub fn invert_key_schedule(expanded: &[u8], round: usize) -> Result<Vec<u8>, &'static str> {
+    let key_words = key_words(expanded.len())?;
+    let total_words = 4 * (key_words + 7);
+    let start = 4 * round;
+    if start + key_words > total_words {
+        return Err("Round is past the end of the key schedule");
+    }
+
+    // `words` holds the `key_words` words starting at word `start`. Every step
+    // recovers the word before the window from the forward relation
+    // w[i] = w[i - key_words] ^ core(w[i - 1]), taking i as the last word.
+    let mut words: Vec<[u8; 4]> = expanded
+        .chunks_exact(4)
+        .map(|word| [word[0], word[1], word[2], word[3]])
+        .collect();
+    for first in (0..start).rev() {
+        let i = first + key_words;
+        let temp = schedule_core(words[key_words - 2], i, key_words, table_sub_byte);
+        let previous = xor_words(words[key_words - 1], temp);
+
+        words.pop();
+        words.insert(0, previous);
+    }
+
+    Ok(words.concat())
+}
+
+/// Returns the key length in words, or an error for an invalid key size.
+fn key_words(key_len: usize) -> Result<usize, &'static str> {
+    match key_len {
+        16 | 24 | 32 => Ok(key_len / 4),
+        _ => Err("Invalid AES key length, it should be 16, 24 or 32 bytes"),
+    }
+}
+
+/// Transforms the word before word `i` of the key schedule: RotWord, SubWord
+/// and the round constant at the start of every key length, SubWord alone in
+/// the middle of an AES-256 key length, and nothing otherwise.
+fn schedule_core(word: [u8; 4], i: usize, key_words: usize, sub_byte: fn(u8) -> u8) -> [u8; 4] {
+    if i % key_words == 0 {
+        [
+            sub_byte(word[1]) ^ RCON[i / key_words - 1],
+            sub_byte(word[2]),
+            sub_byte(word[3]),
+            sub_byte(word[0]),
+        ]
+    } else if key_words > 6 && i % key_words == 4 {
+        word.map(sub_byte)
+    } else {
+        word
+    }
+}
+
+fn xor_words(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    [a[0] ^ b[0], a[1] ^ b[1], a[2] ^ b[2], a[3] ^ b[3]]
+}
+
+/// Groups the schedule words into 16 byte round keys.
+fn words_to_round_keys(words: &[[u8; 4]]) -> Vec<[u8; BLOCK_SIZE]> {
+    words
+        .chunks_exact(4)
+        .map(|chunk| {
+            let mut round_key = [0u8; BLOCK_SIZE];
+            for (i, word) in chunk.iter().enumerate() {
+                round_key[4 * i..4 * i + 4].copy_from_slice(word);
+            }
+            round_key
+        })
+        .collect()
+}
+
+/// XORs the round key into the state.
+fn add_round_key(state: &mut [u8; BLOCK_SIZE], round_key: &[u8; BLOCK_SIZE]) {
+    state
+        .iter_mut()
+        .zip(round_key.iter())
+        .for_each(|(byte, key)| *byte ^= key);
+}
+
+/// Replaces every byte of the state with `sub_byte` of it. This is SubBytes
+/// or InvSubBytes depending on the function passed in.
+fn sub_bytes(state: &mut [u8; BLOCK_SIZE], sub_byte: fn(u8) -> u8) {
+    state.iter_mut().for_each(|byte| *byte = sub_byte(*byte));
+}
+
+/// Looks up the S-box entry of a byte.
+fn table_sub_byte(byte: u8) -> u8 {
+    SBOX[byte as usize]
+}
+
+/// Looks up the inverse S-box entry of a byte.
+fn table_inv_sub_byte(byte: u8) -> u8 {
+    INV_SBOX[byte as usize]
+}
+
+/// Inverts a byte in GF(2^8) as `byte^254`, with zero mapping to zero. The
+/// exponent is public, so the sequence of operations is always the same.
+fn ct_inverse(byte: u8) -> u8 {
+    let x2 = gf_mul(byte, byte);
+    let x3 = gf_mul(x2, byte);
+    let x6 = gf_mul(x3, x3);
+    let x12 = gf_mul(x6, x6);
+    let x15 = gf_mul(x12, x3);
+    let x30 = gf_mul(x15, x15);
+    let x60 = gf_mul(x30, x30);
+    let x120 = gf_mul(x60, x60);
+    let x126 = gf_mul(x120, x6);
+    let x127 = gf_mul(x126, byte);
+
+    gf_mul(x127, x127)
+}
+
+/// Computes the S-box entry of a byte without a table: the inverse in GF(2^8)
+/// followed by the affine transformation.
+fn ct_sub_byte(byte: u8) -> u8 {
+    let b = ct_inverse(byte);
+    b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63
+}
+
+/// Computes the inverse S-box entry of a byte without a table: the inverse
+/// affine transformation followed by the inverse in GF(2^8).
+fn ct_inv_sub_byte(byte: u8) -> u8 {
+    ct_inverse(byte.rotate_left(1) ^ byte.rotate_left(3) ^ byte.rotate_left(6) ^ 0x05)
+}
+
+/// Cyclically shifts row `r` of the state `r` positions to the left.
+fn shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let old = *state;
+    for column in 0..4 {
+        for row in 0..4 {
+            state[4 * column + row] = old[4 * ((column + row) % 4) + row];
+        }
+    }
+}
+
+/// Cyclically shifts row `r` of the state `r` positions to the right.
+fn inv_shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let old = *state;
+    for column in 0..4 {
+        for row in 0..4 {
+            state[4 * ((column + row) % 4) + row] = old[4 * column + row];
+        }
+    }
+}
+
+/// Multiplies a byte by `x` in GF(2^8), reducing by the AES polynomial
+/// x^8 + x^4 + x^3 + x + 1. The reduction is masked in rather than branched
+/// on, so the timing does not depend on the byte.
+pub(crate) fn xtime(byte: u8) -> u8 {
+    (byte << 1) ^ (0x1b & (byte >> 7).wrapping_neg())
+}
+
+/// Multiplies two bytes in GF(2^8). It always runs all eight steps and masks
+/// the additions, so the timing does not depend on the operands.
+pub(crate) fn gf_mul(mut a: u8, b: u8) -> u8 {
+    let mut product = 0;
+    for i in 0..8 {
+        product ^= a & ((b >> i) & 1).wrapping_neg();
+        a = xtime(a);
+    }
+
+    product
+}
+
+/// Multiplies every column of the state by the fixed polynomial
+/// {03}x^3 + {01}x^2 + {01}x + {02}.
+pub(crate) fn mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        let all = a0 ^ a1 ^ a2 ^ a3;
+
+        column[0] ^= all ^ xtime(a0 ^ a1);
+        column[1] ^= all ^ xtime(a1 ^ a2);
+        column[2] ^= all ^ xtime(a2 ^ a3);
+        column[3] ^= all ^ xtime(a3 ^ a0);
+    }
+}
+
+/// Multiplies every column of the state by the inverse polynomial
+/// {0b}x^3 + {0d}x^2 + {09}x + {0e}.
+pub(crate) fn inv_mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+
+        column[0] = gf_mul(a0, 0x0e) ^ gf_mul(a1, 0x0b) ^ gf_mul(a2, 0x0d) ^ gf_mul(a3, 0x09);
+        column[1] = gf_mul(a0, 0x09) ^ gf_mul(a1, 0x0e) ^ gf_mul(a2, 0x0b) ^ gf_mul(a3, 0x0d);
+        column[2] = gf_mul(a0, 0x0d) ^ gf_mul(a1, 0x09) ^ gf_mul(a2, 0x0e) ^ gf_mul(a3, 0x0b);
+        column[3] = gf_mul(a0, 0x0b) ^ gf_mul(a1, 0x0d) ^ gf_mul(a2, 0x09) ^ gf_mul(a3, 0x0e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    /// The backends that can run on this machine.
+    fn backends() -> Vec<Backend> {
+        [Backend::Table, Backend::ConstantTime, Backend::Hardware]
+            .into_iter()
+            .filter(|backend| backend.is_available())
+            .collect()
+    }
+
+    /// Runs one FIPS-197 known answer test in both directions, with every
+    /// available backend.
+    fn check_vector(key: &str, plaintext: &str, ciphertext: &str) {
+        let plaintext: [u8; BLOCK_SIZE] = hex_to_bytes(plaintext).unwrap().try_into().unwrap();
+        let ciphertext: [u8; BLOCK_SIZE] = hex_to_bytes(ciphertext).unwrap().try_into().unwrap();
+
+        for backend in backends() {
+            let aes = Aes::with_backend(&hex_to_bytes(key).unwrap(), backend).unwrap();
+
+            let mut block = plaintext;
+            aes.encrypt_block(&mut block);
+            assert_eq!(block, ciphertext, "{:?}", backend);
+
+            aes.decrypt_block(&mut block);
+            assert_eq!(block, plaintext, "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_fips_197_vectors() {
+        //--------------------------- appendix B ---------------------------//
+
+        check_vector(
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3243f6a8885a308d313198a2e0370734",
+            "3925841d02dc09fbdc118597196a0b32",
+        );
+
+        //-------------------------- AES-128 (C.1) -------------------------//
+
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        );
+
+        //-------------------------- AES-192 (C.2) -------------------------//
+
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        );
+
+        //-------------------------- AES-256 (C.3) -------------------------//
+
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "8ea2b7ca516745bfeafc49904b496089",
+        );
+    }
+
+    #[test]
+    fn test_key_expansion() {
+        // last round key of the FIPS-197 appendix A.1 expansion.
+        let key = hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let round_keys = expand_key(&key).unwrap();
+
+        assert_eq!(round_keys.len(), 11);
+        assert_eq!(
+            round_keys[10].to_vec(),
+            hex_to_bytes("d014f9a8c9ee2589e13f0cc8b6630ca6").unwrap()
+        );
+
+        //------------------------ invalid key size ------------------------//
+
+        assert_eq!(
+            Aes::new(b"too short").err(),
+            Some("Invalid AES key length, it should be 16, 24 or 32 bytes")
+        );
+    }
+
+    #[test]
+    fn test_round_trace() {
+        // FIPS-197 appendix C.1.
+        let key = hex_to_bytes("000102030405060708090a0b0c0d0e0f").unwrap();
+        let aes = Aes::new(&key).unwrap();
+        let mut block: [u8; BLOCK_SIZE] = hex_to_bytes("00112233445566778899aabbccddeeff")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let trace = aes.encrypt_block_traced(&mut block);
+
+        assert_eq!(trace.len(), 11);
+        assert_eq!(
+            trace[1].start.to_vec(),
+            hex_to_bytes("00102030405060708090a0b0c0d0e0f0").unwrap()
+        );
+        assert_eq!(
+            trace[1].after_sub_bytes.unwrap().to_vec(),
+            hex_to_bytes("63cab7040953d051cd60e0e7ba70e18c").unwrap()
+        );
+        assert_eq!(
+            trace[1].after_shift_rows.unwrap().to_vec(),
+            hex_to_bytes("6353e08c0960e104cd70b751bacad0e7").unwrap()
+        );
+        assert_eq!(
+            trace[1].after_mix_columns.unwrap().to_vec(),
+            hex_to_bytes("5f72641557f5bc92f7be3b291db9f91a").unwrap()
+        );
+        assert_eq!(
+            trace[1].round_key.to_vec(),
+            hex_to_bytes("d6aa74fdd2af72fadaa678f1d6ab76fe").unwrap()
+        );
+        assert_eq!(
+            trace[10].round_key.to_vec(),
+            hex_to_bytes("13111d7fe3944a17f307a78b4d2b30c5").unwrap()
+        );
+        assert_eq!(
+            block.to_vec(),
+            hex_to_bytes("69c4e0d86a7b0430d8cdb78070b4c55a").unwrap()
+        );
+
+        // every round starts where the previous one ended.
+        for pair in trace.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+
+    #[test]
+    fn test_invert_key_schedule() {
+        for key_len in [16, 24, 32] {
+            let key: Vec<u8> = (0..key_len).map(|i| (i * 53 + 7) as u8).collect();
+            let expanded = expand_key(&key).unwrap().concat();
+
+            // start from every round key that still has `key_len` bytes of
+            // the schedule after it.
+            let rounds = key_len / 4 + 6;
+            for round in 0..=rounds {
+                let start = round * BLOCK_SIZE;
+                if start + key_len > expanded.len() {
+                    assert_eq!(
+                        invert_key_schedule(&expanded[start - BLOCK_SIZE..][..key_len], round),
+                        Err("Round is past the end of the key schedule")
+                    );
+                    continue;
+                }
+
+                let window = &expanded[start..start + key_len];
+                assert_eq!(invert_key_schedule(window, round).unwrap(), key);
+            }
+        }
+
+        assert!(invert_key_schedule(&[0; 20], 3).is_err());
+    }
+
+    #[test]
+    fn test_reduced_rounds() {
+        let key = hex_to_bytes("000102030405060708090a0b0c0d0e0f1011121314151617").unwrap();
+        let aes = Aes::new(&key).unwrap();
+
+        //------------------- full rounds is plain AES --------------------//
+
+        let full = ReducedAes::new(&key, 12, false).unwrap();
+        let mut block = *b"sixteen byte msg";
+        let mut expected = block;
+        full.encrypt_block(&mut block);
+        aes.encrypt_block(&mut expected);
+        assert_eq!(block, expected);
+
+        //-------------- reduced rounds match the round trace --------------//
+
+        let trace = aes.encrypt_block_traced(&mut b"sixteen byte msg".clone());
+        for round in &trace[1..12] {
+            for final_mix_columns in [false, true] {
+                let reduced = ReducedAes::new(&key, round.round, final_mix_columns).unwrap();
+                let mut block = *b"sixteen byte msg";
+                reduced.encrypt_block(&mut block);
+
+                if final_mix_columns {
+                    assert_eq!(block, round.end);
+                } else {
+                    let mut expected = round.after_shift_rows.unwrap();
+                    add_round_key(&mut expected, &round.round_key);
+                    assert_eq!(block, expected);
+                }
+
+                reduced.decrypt_block(&mut block);
+                assert_eq!(&block, b"sixteen byte msg");
+            }
+        }
+
+        //------------------------ invalid rounds -------------------------//
+
+        assert!(ReducedAes::new(&key, 0, false).is_err());
+        assert!(ReducedAes::new(&key, 13, false).is_err());
+    }
+
+    #[test]
+    fn test_constant_time_sbox() {
+        for byte in 0..=255u8 {
+            assert_eq!(ct_sub_byte(byte), SBOX[byte as usize]);
+            assert_eq!(ct_inv_sub_byte(byte), INV_SBOX[byte as usize]);
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let mut blocks: Vec<[u8; BLOCK_SIZE]> = Vec::new();
+        for i in 0..64u8 {
+            blocks.push(core::array::from_fn(|j| {
+                i.wrapping_mul(37) ^ (j as u8 * 11)
+            }));
+        }
+
+        for key_len in [16, 24, 32] {
+            let key: Vec<u8> = (0..key_len).map(|i| (i * 29 + 1) as u8).collect();
+            let reference = Aes::new(&key).unwrap();
+
+            for backend in backends() {
+                let aes = Aes::with_backend(&key, backend).unwrap();
+                assert_eq!(aes.round_keys, reference.round_keys);
+
+                for block in &blocks {
+                    let mut expected = *block;
+                    reference.encrypt_block(&mut expected);
+
+                    let mut encrypted = *block;
+                    aes.encrypt_block(&mut encrypted);
+                    assert_eq!(encrypted, expected, "{:?}", backend);
+
+                    aes.decrypt_block(&mut encrypted);
+                    assert_eq!(encrypted, *block, "{:?}", backend);
+                }
+            }
+        }
+
+        // the detected backend never falls back to tables.
+        assert_ne!(Backend::detect(), Backend::Table);
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn test_against_openssl() {
+        use openssl::symm::{Cipher, Crypter, Mode};
+
+        let ciphers = [
+            (Cipher::aes_128_ecb(), 16),
+            (Cipher::aes_192_ecb(), 24),
+            (Cipher::aes_256_ecb(), 32),
+        ];
+        for (cipher, key_len) in ciphers {
+            let key: Vec<u8> = (0..key_len).map(|i| (i * 7 + 3) as u8).collect();
+
+            // encrypt a few hundred blocks with openssl, without padding.
+            let plaintext: Vec<u8> = (0..256 * BLOCK_SIZE)
+                .map(|i| (i * 31 % 251) as u8)
+                .collect();
+            let mut crypter = Crypter::new(cipher, Mode::Encrypt, &key, None).unwrap();
+            crypter.pad(false);
+            let mut expected = vec![0u8; plaintext.len() + BLOCK_SIZE];
+            let count = crypter.update(&plaintext, &mut expected).unwrap();
+            expected.truncate(count);
+
+            for backend in backends() {
+                let aes = Aes::with_backend(&key, backend).unwrap();
+                for (chunk, expected) in plaintext
+                    .chunks_exact(BLOCK_SIZE)
+                    .zip(expected.chunks(BLOCK_SIZE))
+                {
+                    let mut block: [u8; BLOCK_SIZE] = chunk.try_into().unwrap();
+                    aes.encrypt_block(&mut block);
+                    assert_eq!(block, expected);
+
+                    aes.decrypt_block(&mut block);
+                    assert_eq!(block, chunk);
+                }
+            }
+        }
+    }
+}
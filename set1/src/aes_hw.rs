@@ -0,0 +1,158 @@
+//! AES block function on the AES-NI (x86-64) and ARMv8 cryptography
+//! instructions. Callers must check `is_supported` first; the `Aes` type does
+//! that once, when the hardware backend is picked.
+
+use crate::aes::BLOCK_SIZE;
+
+/// Checks at runtime whether the CPU has AES instructions.
+pub(crate) fn is_supported() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::arch::is_x86_feature_detected!("aes") && std::arch::is_x86_feature_detected!("sse2")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("aes")
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+/// Encrypts a block with the expanded round keys.
+///
+/// # Safety
+/// `is_supported` must have returned `true`.
+pub(crate) unsafe fn encrypt_block(round_keys: &[[u8; BLOCK_SIZE]], block: &mut [u8; BLOCK_SIZE]) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        x86::encrypt_block(round_keys, block)
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        arm::encrypt_block(round_keys, block)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = (round_keys, block);
+        unreachable!()
+    }
+}
+
+/// Decrypts a block with the round keys of the equivalent inverse cipher,
+/// see `Aes::with_backend`: the middle ones have gone through InvMixColumns.
+///
+/// # Safety
+/// `is_supported` must have returned `true`.
+pub(crate) unsafe fn decrypt_block(
+    decryption_keys: &[[u8; BLOCK_SIZE]],
+    block: &mut [u8; BLOCK_SIZE],
+) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        x86::decrypt_block(decryption_keys, block)
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        arm::decrypt_block(decryption_keys, block)
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = (decryption_keys, block);
+        unreachable!()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::BLOCK_SIZE;
+    use std::arch::x86_64::*;
+
+    unsafe fn load(bytes: &[u8; BLOCK_SIZE]) -> __m128i {
+        _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+    }
+
+    /// `aesenc` runs a whole middle round and `aesenclast` the final round
+    /// without MixColumns.
+    #[target_feature(enable = "aes,sse2")]
+    pub(super) unsafe fn encrypt_block(
+        round_keys: &[[u8; BLOCK_SIZE]],
+        block: &mut [u8; BLOCK_SIZE],
+    ) {
+        let rounds = round_keys.len() - 1;
+
+        let mut state = _mm_xor_si128(load(block), load(&round_keys[0]));
+        for round_key in &round_keys[1..rounds] {
+            state = _mm_aesenc_si128(state, load(round_key));
+        }
+        state = _mm_aesenclast_si128(state, load(&round_keys[rounds]));
+
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state);
+    }
+
+    /// `aesdec` implements the equivalent inverse cipher, which expects the
+    /// middle round keys to have gone through InvMixColumns.
+    #[target_feature(enable = "aes,sse2")]
+    pub(super) unsafe fn decrypt_block(
+        round_keys: &[[u8; BLOCK_SIZE]],
+        block: &mut [u8; BLOCK_SIZE],
+    ) {
+        let rounds = round_keys.len() - 1;
+
+        let mut state = _mm_xor_si128(load(block), load(&round_keys[rounds]));
+        for round_key in round_keys[1..rounds].iter().rev() {
+            state = _mm_aesdec_si128(state, load(round_key));
+        }
+        state = _mm_aesdeclast_si128(state, load(&round_keys[0]));
+
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use super::BLOCK_SIZE;
+    use std::arch::aarch64::*;
+
+    /// `aese` is AddRoundKey, SubBytes and ShiftRows, and `aesmc` is
+    /// MixColumns, so the last round key is XORed in separately.
+    #[target_feature(enable = "aes")]
+    pub(super) unsafe fn encrypt_block(
+        round_keys: &[[u8; BLOCK_SIZE]],
+        block: &mut [u8; BLOCK_SIZE],
+    ) {
+        let rounds = round_keys.len() - 1;
+
+        let mut state = vld1q_u8(block.as_ptr());
+        for round_key in &round_keys[..rounds - 1] {
+            state = vaesmcq_u8(vaeseq_u8(state, vld1q_u8(round_key.as_ptr())));
+        }
+        state = vaeseq_u8(state, vld1q_u8(round_keys[rounds - 1].as_ptr()));
+        state = veorq_u8(state, vld1q_u8(round_keys[rounds].as_ptr()));
+
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+
+    /// `aesd` is AddRoundKey, InvShiftRows and InvSubBytes. Moving
+    /// InvMixColumns (`aesimc`) in front of the round key addition is why
+    /// the middle round keys went through InvMixColumns too.
+    #[target_feature(enable = "aes")]
+    pub(super) unsafe fn decrypt_block(
+        round_keys: &[[u8; BLOCK_SIZE]],
+        block: &mut [u8; BLOCK_SIZE],
+    ) {
+        let rounds = round_keys.len() - 1;
+
+        let mut state = vaesdq_u8(
+            vld1q_u8(block.as_ptr()),
+            vld1q_u8(round_keys[rounds].as_ptr()),
+        );
+        for round_key in round_keys[1..rounds].iter().rev() {
+            state = vaesdq_u8(vaesimcq_u8(state), vld1q_u8(round_key.as_ptr()));
+        }
+        state = veorq_u8(state, vld1q_u8(round_keys[0].as_ptr()));
+
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+}
@@ -116,7 +116,7 @@ pub fn bytes_to_base64(bytes: &[u8]) -> String {
 
     // checking for remainder.
     let remainder = bytes.chunks_exact(3).remainder();
-    let _ = match remainder.len() {
+    match remainder.len() {
         1 => {
             // if there is only 1 byte, then the second and third characters
             // will be padded with '='.
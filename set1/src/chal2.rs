@@ -34,9 +34,46 @@ pub fn fixed_xor<'a>(buffer1: &str, buffer2: &str) -> Result<String, &'a str> {
     Ok(xor)
 }
 
+/// This method XORs two equal length byte buffers and returns the result. If
+/// the buffers are not equal length, then an error is returned.
+///
+/// # Examples
+/// ```
+/// use set1::chal2::xor_bytes;
+/// let xor = xor_bytes(&[0x1c, 0x01, 0x11], &[0x68, 0x69, 0x74]).unwrap();
+///
+/// assert_eq!(xor, vec![0x74, 0x68, 0x65]);
+/// ```
+pub fn xor_bytes<'a>(buffer1: &[u8], buffer2: &[u8]) -> Result<Vec<u8>, &'a str> {
+    if buffer1.len() != buffer2.len() {
+        return Err("Buffers are not the same length.");
+    }
+
+    Ok(buffer1.iter().zip(buffer2).map(|(a, b)| a ^ b).collect())
+}
+
+/// This method XORs `other` into `buffer` byte by byte. It stops at the end of
+/// the shorter buffer, so a longer keystream can be XORed into a short tail.
+///
+/// # Examples
+/// ```
+/// use set1::chal2::xor_in_place;
+/// let mut buffer = vec![0x1c, 0x01];
+/// xor_in_place(&mut buffer, &[0x68, 0x69, 0x74]);
+///
+/// assert_eq!(buffer, vec![0x74, 0x68]);
+/// ```
+pub fn xor_in_place(buffer: &mut [u8], other: &[u8]) {
+    buffer
+        .iter_mut()
+        .zip(other)
+        .for_each(|(byte, other)| *byte ^= other);
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::chal1::hex_to_bytes;
 
     #[test]
     fn test_fixed_xor() {
@@ -56,4 +93,25 @@ mod test {
 
         assert_eq!(xor, Err("Buffers are not the same length."));
     }
+
+    #[test]
+    fn test_xor_bytes() {
+        let buffer1 = hex_to_bytes("1c0111001f010100061a024b53535009181c").unwrap();
+        let buffer2 = hex_to_bytes("686974207468652062756c6c277320657965").unwrap();
+        let expected = hex_to_bytes("746865206b696420646f6e277420706c6179").unwrap();
+
+        assert_eq!(xor_bytes(&buffer1, &buffer2), Ok(expected.clone()));
+
+        // xoring in place gives the same result.
+        let mut buffer = buffer1.clone();
+        xor_in_place(&mut buffer, &buffer2);
+        assert_eq!(buffer, expected);
+
+        //----------- test buffers of different lengths -----------//
+
+        assert_eq!(
+            xor_bytes(&buffer1, &buffer2[1..]),
+            Err("Buffers are not the same length.")
+        );
+    }
 }
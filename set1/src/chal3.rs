@@ -47,7 +47,7 @@ fn get_char_freq_matrix() -> [[f64; 256]; 256] {
     let mut char_freq_matrix = [[0.0; 256]; 256];
 
     // Iterate over the frequencies and populate the matrix.
-    char_freq.iter().enumerate().for_each(|(_, (k, v))| {
+    char_freq.iter().for_each(|(k, v)| {
         char_freq_matrix
             .iter_mut()
             .enumerate()
@@ -45,7 +45,7 @@ pub fn repeating_key_xor(plaintext: &str, key: &str) -> String {
 ///
 /// # Returns
 ///  A hex string.
-fn bytes_to_hex(bytes: &[u8]) -> String {
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
     let mut hex_string = String::new();
 
     // iterate over the bytes and convert them to hex.
@@ -1,7 +1,9 @@
 use crate::{chal1::hex_to_bytes, chal3::get_scores, chal5::repeating_key_xor};
 use std::{collections::HashMap, fs};
 
-pub(crate) fn base64_to_bytes(cipher: &str) -> Result<Vec<u8>, &str> {
+/// This method converts a base64 string into raw bytes. The length of the
+/// string should be a multiple of 4, otherwise it will return an error.
+pub fn base64_to_bytes(cipher: &str) -> Result<Vec<u8>, &str> {
     // the length of the cipher should be divisible by 4.
     if cipher.len() % 4 != 0 {
         return Err("Invalid base64 string, it should be divisible by 4");
@@ -81,17 +83,16 @@ pub(crate) fn base64_to_bytes(cipher: &str) -> Result<Vec<u8>, &str> {
     // the last chunk will be handled separately.
     for i in (0..size - 4).step_by(4) {
         // first octet is the first 6 bits of the first byte and the first 2 bits of the second byte.
-        let first_octet = (char_set.get(&cipher.chars().nth(i).unwrap()).unwrap() << 2
-            | char_set.get(&cipher.chars().nth(i + 1).unwrap()).unwrap() >> 4)
-            as u8;
+        let first_octet = char_set.get(&cipher.chars().nth(i).unwrap()).unwrap() << 2
+            | char_set.get(&cipher.chars().nth(i + 1).unwrap()).unwrap() >> 4;
 
         // second octet is the last 4 bits of the second byte and the first 4 bits of the third byte.
         let second_octet = char_set.get(&cipher.chars().nth(i + 1).unwrap()).unwrap() << 4
-            | char_set.get(&cipher.chars().nth(i + 2).unwrap()).unwrap() >> 2 as u8;
+            | char_set.get(&cipher.chars().nth(i + 2).unwrap()).unwrap() >> 2;
 
         // third octet is the last 2 bits of the third byte and the first 6 bits of the fourth byte.
         let third_octet = char_set.get(&cipher.chars().nth(i + 2).unwrap()).unwrap() << 6
-            | *char_set.get(&cipher.chars().nth(i + 3).unwrap()).unwrap() as u8;
+            | *char_set.get(&cipher.chars().nth(i + 3).unwrap()).unwrap();
 
         // pushing the octets to the cipher bytes.
         cipher_bytes.push(first_octet);
@@ -102,40 +103,37 @@ pub(crate) fn base64_to_bytes(cipher: &str) -> Result<Vec<u8>, &str> {
     // processing the last chunk.
     let last_chunk = &cipher[size - 4..];
     // check the occurence of '=' in the last chunk.
-    let _ = match last_chunk.matches('=').count() {
+    match last_chunk.matches('=').count() {
         1 => {
             // if there is one `=` in the last chunk, then there would be only two octets in the last chunk.
-            let first_octet = (char_set.get(&last_chunk.chars().nth(0).unwrap()).unwrap() << 2
-                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4)
-                as u8;
+            let first_octet = char_set.get(&last_chunk.chars().next().unwrap()).unwrap() << 2
+                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4;
 
             let second_octet = char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() << 4
-                | char_set.get(&last_chunk.chars().nth(2).unwrap()).unwrap() >> 2 as u8;
+                | char_set.get(&last_chunk.chars().nth(2).unwrap()).unwrap() >> 2;
 
             cipher_bytes.push(first_octet);
             cipher_bytes.push(second_octet);
         }
         2 => {
             // if there are two `=` in the last chunk, then there will be only one octet in the last chunk.
-            let first_octet = (char_set.get(&last_chunk.chars().nth(0).unwrap()).unwrap() << 2
-                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4)
-                as u8;
+            let first_octet = char_set.get(&last_chunk.chars().next().unwrap()).unwrap() << 2
+                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4;
 
             cipher_bytes.push(first_octet);
         }
         0 => {
             // first octet is the first 6 bits of the first byte and the first 2 bits of the second byte.
-            let first_octet = (char_set.get(&last_chunk.chars().nth(0).unwrap()).unwrap() << 2
-                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4)
-                as u8;
+            let first_octet = char_set.get(&last_chunk.chars().next().unwrap()).unwrap() << 2
+                | char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() >> 4;
 
             // second octet is the last 4 bits of the second byte and the first 4 bits of the third byte.
             let second_octet = char_set.get(&last_chunk.chars().nth(1).unwrap()).unwrap() << 4
-                | char_set.get(&last_chunk.chars().nth(2).unwrap()).unwrap() >> 2 as u8;
+                | char_set.get(&last_chunk.chars().nth(2).unwrap()).unwrap() >> 2;
 
             // third octet is the last 2 bits of the third byte and the first 6 bits of the fourth byte.
             let third_octet = char_set.get(&last_chunk.chars().nth(2).unwrap()).unwrap() << 6
-                | *char_set.get(&last_chunk.chars().nth(3).unwrap()).unwrap() as u8;
+                | *char_set.get(&last_chunk.chars().nth(3).unwrap()).unwrap();
 
             // pushing the octets to the cipher bytes.
             cipher_bytes.push(first_octet);
@@ -158,13 +156,10 @@ fn edit_distance<'a>(s1: &[u8], s2: &[u8]) -> Result<usize, &'a str> {
 
     let mut distance: usize = 0;
 
-    s1.iter()
-        .zip(s2.iter())
-        .enumerate()
-        .for_each(|(_, (c1, c2))| {
-            let xor = c1 ^ c2;
-            distance += xor.count_ones() as usize;
-        });
+    s1.iter().zip(s2.iter()).for_each(|(c1, c2)| {
+        let xor = c1 ^ c2;
+        distance += xor.count_ones() as usize;
+    });
 
     Ok(distance)
 }
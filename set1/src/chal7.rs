@@ -1,5 +1,9 @@
-use crate::chal6::base64_to_bytes;
-use openssl::symm::{decrypt, Cipher};
+use crate::{
+    aes::{Aes, BLOCK_SIZE},
+    chal6::base64_to_bytes,
+    modes::{BlockMode, Ecb},
+    padding::{Padding, Pkcs7},
+};
 use std::fs;
 
 /// This function decrypts the ciphertext in set1/data/chal7/7.txt using AES in ECB mode.
@@ -17,8 +21,7 @@ pub fn aes_in_ecb_mode() {
 
     // decrypt the ciphertext.
     let key = "YELLOW SUBMARINE";
-    let decrypt_bytes =
-        decrypt(Cipher::aes_128_ecb(), key.as_bytes(), None, &cipher_bytes).unwrap();
+    let decrypt_bytes = aes_ecb_decrypt(&cipher_bytes, key.as_bytes(), &Pkcs7).unwrap();
 
     println!(
         "Decryption: {}",
@@ -28,3 +31,153 @@ pub fn aes_in_ecb_mode() {
     // save the decrypted text to a file.
     fs::write("set1/data/chal7/7_decryption.txt", decrypt_bytes).unwrap();
 }
+
+/// This function pads the plaintext with the given padding and encrypts every
+/// block independently with AES. The key can be 16, 24 or 32 bytes long.
+/// Returns an error for a bad key size, or if the padding rejects the data.
+///
+/// # Examples
+/// ```
+/// use set1::chal7::{aes_ecb_decrypt, aes_ecb_encrypt};
+/// use set1::padding::{NoPadding, Pkcs7};
+///
+/// let ciphertext = aes_ecb_encrypt(b"ICE ICE BABY", b"YELLOW SUBMARINE", &Pkcs7).unwrap();
+/// assert_eq!(ciphertext.len(), 16);
+///
+/// let plaintext = aes_ecb_decrypt(&ciphertext, b"YELLOW SUBMARINE", &Pkcs7).unwrap();
+/// assert_eq!(plaintext, b"ICE ICE BABY");
+///
+/// // without padding the plaintext must be a whole number of blocks.
+/// assert!(aes_ecb_encrypt(b"ICE ICE BABY", b"YELLOW SUBMARINE", &NoPadding).is_err());
+/// ```
+pub fn aes_ecb_encrypt(
+    plaintext: &[u8],
+    key: &[u8],
+    padding: &dyn Padding,
+) -> Result<Vec<u8>, &'static str> {
+    let aes = Aes::new(key)?;
+    let padded = padding.pad(plaintext, BLOCK_SIZE)?;
+
+    Ecb::new(aes).encrypt(&padded)
+}
+
+/// This function decrypts every block of the ciphertext independently with the
+/// given key and strips the padding from the result. The ciphertext must be a
+/// whole number of blocks. Returns an error for a bad key size, a partial
+/// block, or malformed padding.
+pub fn aes_ecb_decrypt(
+    ciphertext: &[u8],
+    key: &[u8],
+    padding: &dyn Padding,
+) -> Result<Vec<u8>, &'static str> {
+    let plaintext = Ecb::new(Aes::new(key)?).decrypt(ciphertext)?;
+
+    padding.unpad(&plaintext, BLOCK_SIZE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decrypt_aes_ecb() {
+        let cipher = include_str!("../data/chal7/7.txt").replace('\n', "");
+        let cipher_bytes = base64_to_bytes(&cipher).unwrap();
+        let expected = include_bytes!("../data/chal7/7_decryption.txt");
+
+        let plaintext = aes_ecb_decrypt(&cipher_bytes, b"YELLOW SUBMARINE", &Pkcs7).unwrap();
+
+        assert_eq!(plaintext, expected);
+        assert_eq!(
+            aes_ecb_encrypt(expected, b"YELLOW SUBMARINE", &Pkcs7).unwrap(),
+            cipher_bytes
+        );
+    }
+
+    #[test]
+    fn test_aes_ecb_key_sizes() {
+        use crate::chal1::hex_to_bytes;
+        use crate::padding::NoPadding;
+
+        // first block of the SP 800-38A F.1 ECB vectors, for every key size.
+        let plaintext = hex_to_bytes("6bc1bee22e409f96e93d7e117393172a").unwrap();
+        let vectors = [
+            (
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3ad77bb40d7a3660a89ecaf32466ef97",
+            ),
+            (
+                "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+                "bd334f1d6e45f25ff712a214571fa5cc",
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "f3eed1bdb5d2a03c064b5a7e3db181f8",
+            ),
+        ];
+        for (key, ciphertext) in vectors {
+            let key = hex_to_bytes(key).unwrap();
+            let ciphertext = hex_to_bytes(ciphertext).unwrap();
+
+            assert_eq!(
+                aes_ecb_encrypt(&plaintext, &key, &NoPadding).unwrap(),
+                ciphertext
+            );
+            assert_eq!(
+                aes_ecb_decrypt(&ciphertext, &key, &NoPadding).unwrap(),
+                plaintext
+            );
+        }
+    }
+
+    #[test]
+    fn test_aes_ecb_errors() {
+        assert_eq!(
+            aes_ecb_encrypt(b"ICE ICE BABY", b"SUBMARINE", &Pkcs7),
+            Err("Invalid AES key length, it should be 16, 24 or 32 bytes")
+        );
+        assert_eq!(
+            aes_ecb_decrypt(b"ICE ICE BABY", b"YELLOW SUBMARINE", &Pkcs7),
+            Err("Data length should be a multiple of the block size")
+        );
+
+        // a block that decrypts to garbage has no valid padding.
+        assert_eq!(
+            aes_ecb_decrypt(&[0; 16], b"YELLOW SUBMARINE", &Pkcs7),
+            Err("Invalid PKCS#7 padding")
+        );
+    }
+
+    #[test]
+    fn test_aes_ecb_custom_padding() {
+        // space padding only works for text that doesn't end in a space,
+        // but shows that callers can bring their own scheme.
+        struct Spaces;
+
+        impl Padding for Spaces {
+            fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+                let mut padded = data.to_vec();
+                padded.resize(
+                    (data.len() + block_size - 1) / block_size * block_size,
+                    b' ',
+                );
+                Ok(padded)
+            }
+
+            fn unpad(&self, data: &[u8], _block_size: usize) -> Result<Vec<u8>, &'static str> {
+                let end = data
+                    .iter()
+                    .rposition(|byte| *byte != b' ')
+                    .map_or(0, |i| i + 1);
+                Ok(data[..end].to_vec())
+            }
+        }
+
+        let ciphertext = aes_ecb_encrypt(b"ICE ICE BABY", b"YELLOW SUBMARINE", &Spaces).unwrap();
+        assert_eq!(ciphertext.len(), 16);
+        assert_eq!(
+            aes_ecb_decrypt(&ciphertext, b"YELLOW SUBMARINE", &Spaces).unwrap(),
+            b"ICE ICE BABY"
+        );
+    }
+}
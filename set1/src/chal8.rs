@@ -22,20 +22,25 @@ pub fn detect_aes_in_ecb_mode() {
 
 /// This function checks if a cipher has duplicate chunks of bytes. If it does, it returns true.
 /// Otherwise, it returns false.
-fn duplicate_checker(cipher: &[u8], chunks_len: usize) -> bool {
+///
+/// # Examples
+/// ```
+/// use set1::chal8::duplicate_checker;
+///
+/// assert!(duplicate_checker(b"YELLOW SUBMARINEYELLOW SUBMARINE", 16));
+/// assert!(!duplicate_checker(b"YELLOW SUBMARINEPURPLE SUBMARINE", 16));
+/// ```
+pub fn duplicate_checker(cipher: &[u8], chunks_len: usize) -> bool {
     let mut has_duplicate = false;
 
     let mut map = HashMap::new();
-    cipher
-        .chunks_exact(chunks_len)
-        .enumerate()
-        .for_each(|(_, chunk)| {
-            if map.contains_key(chunk) {
-                has_duplicate = true;
-            } else {
-                map.insert(chunk, 1);
-            }
-        });
+    cipher.chunks_exact(chunks_len).for_each(|chunk| {
+        if map.contains_key(chunk) {
+            has_duplicate = true;
+        } else {
+            map.insert(chunk, 1);
+        }
+    });
 
     has_duplicate
 }
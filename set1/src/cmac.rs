@@ -0,0 +1,133 @@
+//! AES-CMAC (NIST SP 800-38B, RFC 4493)
+
+use crate::{
+    aes::{Aes, Backend, BLOCK_SIZE},
+    chal2::xor_in_place,
+};
+
+/// AES-CMAC. A CBC-MAC with a zero IV where the last block is XORed with one
+/// of two subkeys derived from the key, depending on whether it had to be
+/// padded, which makes it safe for messages of any length.
+///
+/// # Examples
+/// ```
+/// use set1::cmac::Cmac;
+///
+/// let cmac = Cmac::new(b"YELLOW SUBMARINE").unwrap();
+/// assert_ne!(cmac.mac(b"attack at dawn"), cmac.mac(b"attack at dusk"));
+/// ```
+#[derive(Clone)]
+pub struct Cmac {
+    aes: Aes,
+    /// XORed into a complete last block.
+    k1: [u8; BLOCK_SIZE],
+    /// XORed into a padded last block.
+    k2: [u8; BLOCK_SIZE],
+}
+
+impl Cmac {
+    /// Uses `Backend::detect()`, so the key never goes through the table
+    /// backend. Returns an error if the key is not a valid AES key.
+    pub fn new(key: &[u8]) -> Result<Self, &'static str> {
+        Cmac::with_backend(key, Backend::detect())
+    }
+
+    /// Returns an error if the key is not a valid AES key or the backend is
+    /// not supported by this CPU.
+    pub fn with_backend(key: &[u8], backend: Backend) -> Result<Self, &'static str> {
+        let aes = Aes::with_backend(key, backend)?;
+
+        let mut l = [0u8; BLOCK_SIZE];
+        aes.encrypt_block(&mut l);
+        let k1 = dbl(&l);
+        let k2 = dbl(&k1);
+
+        Ok(Cmac { aes, k1, k2 })
+    }
+
+    /// Returns the 16 byte tag of `message`.
+    pub fn mac(&self, message: &[u8]) -> [u8; BLOCK_SIZE] {
+        // the last block is the one that gets the subkey, and an empty message
+        // has a single, padded, last block.
+        let last_start = message.len().saturating_sub(1) / BLOCK_SIZE * BLOCK_SIZE;
+        let (blocks, last) = message.split_at(last_start);
+
+        let mut state = [0u8; BLOCK_SIZE];
+        for block in blocks.chunks_exact(BLOCK_SIZE) {
+            xor_in_place(&mut state, block);
+            self.aes.encrypt_block(&mut state);
+        }
+
+        let mut last_block = [0u8; BLOCK_SIZE];
+        last_block[..last.len()].copy_from_slice(last);
+        if last.len() == BLOCK_SIZE {
+            xor_in_place(&mut last_block, &self.k1);
+        } else {
+            last_block[last.len()] = 0x80;
+            xor_in_place(&mut last_block, &self.k2);
+        }
+
+        xor_in_place(&mut state, &last_block);
+        self.aes.encrypt_block(&mut state);
+        state
+    }
+}
+
+/// Doubles a block in GF(2^128), reading it as a big-endian number: a left
+/// shift, with x^128 = x^7 + x^2 + x + 1 folded into the last byte.
+pub(crate) fn dbl(block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let value = u128::from_be_bytes(*block);
+    let carry = (value >> 127) as u8;
+    let mut doubled = (value << 1).to_be_bytes();
+    doubled[BLOCK_SIZE - 1] ^= 0x87 & carry.wrapping_neg();
+
+    doubled
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    #[test]
+    fn test_rfc_4493_vectors() {
+        let cmac = Cmac::new(&hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c").unwrap()).unwrap();
+        let message = hex_to_bytes(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+
+        // subkeys from section 4.
+        assert_eq!(
+            cmac.k1.to_vec(),
+            hex_to_bytes("fbeed618357133667c85e08f7236a8de").unwrap()
+        );
+        assert_eq!(
+            cmac.k2.to_vec(),
+            hex_to_bytes("f7ddac306ae266ccf90bc11ee46d513b").unwrap()
+        );
+
+        let vectors = [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ];
+        for (len, tag) in vectors {
+            assert_eq!(
+                cmac.mac(&message[..len]).to_vec(),
+                hex_to_bytes(tag).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        let cmac = Cmac::new(b"YELLOW SUBMARINE").unwrap();
+        assert_ne!(cmac.aes.backend(), Backend::Table);
+
+        let table = Cmac::with_backend(b"YELLOW SUBMARINE", Backend::Table).unwrap();
+        assert_eq!(cmac.mac(b"attack at dawn"), table.mac(b"attack at dawn"));
+    }
+}
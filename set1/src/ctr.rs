@@ -0,0 +1,487 @@
+//! Seekable counter (CTR) mode
+
+use crate::{
+    chal2::xor_in_place,
+    modes::{BlockCipher, BlockMode},
+};
+
+/// Byte order of the counter inside the counter block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// Describes how a counter block is built: the nonce comes first, followed by
+/// the counter. The two widths must add up to the block size of the cipher.
+///
+/// # Examples
+/// ```
+/// use set1::ctr::CounterLayout;
+///
+/// let block = CounterLayout::NONCE_64_COUNTER_64_LE.counter_block(&[0; 8], 1).unwrap();
+/// assert_eq!(block, [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+///
+/// let block = CounterLayout::NONCE_96_COUNTER_32_BE.counter_block(&[0xff; 12], 2).unwrap();
+/// assert_eq!(block[12..], [0, 0, 0, 2]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterLayout {
+    /// Width of the nonce in bytes.
+    pub nonce_len: usize,
+    /// Width of the counter in bytes, at most 16.
+    pub counter_len: usize,
+    pub counter_endianness: Endianness,
+}
+
+impl CounterLayout {
+    /// 64-bit nonce followed by a 64-bit little-endian block counter, the
+    /// layout used by the cryptopals challenges.
+    pub const NONCE_64_COUNTER_64_LE: CounterLayout = CounterLayout {
+        nonce_len: 8,
+        counter_len: 8,
+        counter_endianness: Endianness::Little,
+    };
+
+    /// 96-bit nonce followed by a 32-bit big-endian block counter, as in GCM
+    /// and RFC 3686.
+    pub const NONCE_96_COUNTER_32_BE: CounterLayout = CounterLayout {
+        nonce_len: 12,
+        counter_len: 4,
+        counter_endianness: Endianness::Big,
+    };
+
+    /// The whole block is one big-endian counter, as in NIST SP 800-38A. The
+    /// counter is at most 16 bytes wide, so for a wider block the bytes above
+    /// it are a fixed nonce.
+    pub fn full_block(block_size: usize) -> CounterLayout {
+        CounterLayout {
+            nonce_len: block_size.saturating_sub(16),
+            counter_len: block_size.min(16),
+            counter_endianness: Endianness::Big,
+        }
+    }
+
+    /// Returns the size of the counter block.
+    pub fn block_size(&self) -> usize {
+        self.nonce_len + self.counter_len
+    }
+
+    /// Checks that the counter width is supported and that `nonce` fits the
+    /// layout.
+    fn check(&self, nonce: &[u8]) -> Result<(), &'static str> {
+        if self.counter_len == 0 || self.counter_len > 16 {
+            return Err("Counter width should be between 1 and 16 bytes");
+        }
+        if nonce.len() != self.nonce_len {
+            return Err("Nonce length does not match the counter layout");
+        }
+        Ok(())
+    }
+
+    /// Returns the mask that keeps a counter value inside the counter width,
+    /// so that the counter wraps around instead of spilling into the nonce.
+    fn counter_mask(&self) -> u128 {
+        if self.counter_len >= 16 {
+            u128::MAX
+        } else {
+            (1u128 << (8 * self.counter_len)) - 1
+        }
+    }
+
+    /// Builds the counter block for the given nonce and counter value. The
+    /// counter is reduced modulo the counter width. Returns an error if the
+    /// counter width is not between 1 and 16 bytes or the nonce does not
+    /// match the layout.
+    pub fn counter_block(&self, nonce: &[u8], counter: u128) -> Result<Vec<u8>, &'static str> {
+        self.check(nonce)?;
+
+        let counter = (counter & self.counter_mask()).to_le_bytes();
+        let mut block = nonce.to_vec();
+
+        match self.counter_endianness {
+            Endianness::Little => block.extend_from_slice(&counter[..self.counter_len]),
+            Endianness::Big => block.extend(counter[..self.counter_len].iter().rev()),
+        }
+
+        Ok(block)
+    }
+}
+
+/// Counter mode with a configurable counter block layout. The keystream can be
+/// read from any byte offset, which makes it possible to seek inside a message
+/// and to rewrite part of a ciphertext without touching the rest of it.
+///
+/// # Examples
+/// ```
+/// use set1::{aes::Aes, ctr::{CounterLayout, Ctr}, modes::BlockMode};
+///
+/// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+/// let layout = CounterLayout::NONCE_64_COUNTER_64_LE;
+/// let mut ctr = Ctr::with_layout(&aes, &[0; 8], layout).unwrap();
+/// let ciphertext = ctr.encrypt(b"attack at dawn, not at dusk").unwrap();
+///
+/// // decrypt only the tail of the message.
+/// let mut ctr = Ctr::with_layout(&aes, &[0; 8], layout).unwrap();
+/// ctr.seek(16);
+/// assert_eq!(ctr.decrypt(&ciphertext[16..]).unwrap(), b"not at dusk");
+/// ```
+pub struct Ctr<C> {
+    cipher: C,
+    layout: CounterLayout,
+    nonce: Vec<u8>,
+    /// Counter value of the first keystream block.
+    initial_counter: u128,
+    /// Byte offset of the next keystream byte.
+    position: u64,
+    /// Index and content of the last keystream block that was generated.
+    cached_block: Option<(u64, Vec<u8>)>,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// Creates the mode from the initial counter block, treating the whole
+    /// block as a big-endian counter (NIST SP 800-38A), or its low 16 bytes
+    /// for a wider block. Returns an error if it is not exactly one block
+    /// long.
+    pub fn new(cipher: C, initial_counter: &[u8]) -> Result<Self, &'static str> {
+        let layout = CounterLayout::full_block(cipher.block_size());
+        if initial_counter.len() != layout.block_size() {
+            return Err("IV length should be equal to the block size");
+        }
+
+        let (nonce, counter) = initial_counter.split_at(layout.nonce_len);
+        let mut ctr = Ctr::with_layout(cipher, nonce, layout)?;
+        ctr.initial_counter = counter
+            .iter()
+            .fold(0, |counter, byte| counter << 8 | *byte as u128);
+
+        Ok(ctr)
+    }
+
+    /// Creates the mode from a nonce and a counter layout. The counter starts
+    /// at zero. Returns an error if the layout does not match the block size of
+    /// the cipher or the nonce does not match the layout.
+    pub fn with_layout(
+        cipher: C,
+        nonce: &[u8],
+        layout: CounterLayout,
+    ) -> Result<Self, &'static str> {
+        if layout.block_size() != cipher.block_size() {
+            return Err("Nonce and counter widths should add up to the block size");
+        }
+        layout.check(nonce)?;
+
+        Ok(Ctr {
+            cipher,
+            layout,
+            nonce: nonce.to_vec(),
+            initial_counter: 0,
+            position: 0,
+            cached_block: None,
+        })
+    }
+
+    /// Sets the value of the counter for the first keystream block.
+    pub fn set_initial_counter(&mut self, counter: u128) {
+        self.initial_counter = counter;
+        self.cached_block = None;
+    }
+
+    /// Returns the byte offset the next call will start from.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Moves to the given byte offset of the keystream. Reading past
+    /// `u64::MAX` bytes fails instead of wrapping around.
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    /// Returns the keystream block with the given index.
+    fn keystream_block(&mut self, index: u64) -> &[u8] {
+        let cached = matches!(&self.cached_block, Some((cached, _)) if *cached == index);
+        if !cached {
+            let counter = self.initial_counter.wrapping_add(index as u128);
+            // the layout and nonce were checked in `with_layout`.
+            let mut block = self.layout.counter_block(&self.nonce, counter).unwrap();
+            self.cipher.encrypt_block(&mut block);
+            self.cached_block = Some((index, block));
+        }
+
+        &self.cached_block.as_ref().unwrap().1
+    }
+
+    /// Returns `len` keystream bytes starting at `offset`, without moving the
+    /// current position.
+    pub fn keystream_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, &'static str> {
+        let mut keystream = vec![0u8; len];
+        let position = self.position;
+
+        self.seek(offset);
+        let applied = self.apply_keystream(&mut keystream);
+        self.seek(position);
+        applied?;
+
+        Ok(keystream)
+    }
+
+    /// XORs the keystream into `data`, starting at the current position.
+    /// Returns an error, leaving `data` alone, if the keystream position
+    /// would go past `u64::MAX`, since it would then reuse the keystream.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        let block_size = self.layout.block_size() as u64;
+        self.position
+            .checked_add(data.len() as u64)
+            .ok_or("The keystream position would wrap around")?;
+        let mut data = data;
+
+        while !data.is_empty() {
+            let index = self.position / block_size;
+            let offset = (self.position % block_size) as usize;
+            let len = data.len().min(block_size as usize - offset);
+
+            let (head, tail) = data.split_at_mut(len);
+            xor_in_place(head, &self.keystream_block(index)[offset..]);
+
+            // checked above for the whole of `data`.
+            self.position += len as u64;
+            data = tail;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the plaintext at `offset` inside `ciphertext` with
+    /// `new_plaintext` and re-encrypts it under the same keystream. The rest of
+    /// the ciphertext and the current position are left untouched. The
+    /// ciphertext must start at keystream offset zero.
+    pub fn edit(
+        &mut self,
+        ciphertext: &mut [u8],
+        offset: usize,
+        new_plaintext: &[u8],
+    ) -> Result<(), &'static str> {
+        let end = offset
+            .checked_add(new_plaintext.len())
+            .filter(|end| *end <= ciphertext.len())
+            .ok_or("Edited region should lie inside the ciphertext")?;
+
+        let region = &mut ciphertext[offset..end];
+        region.copy_from_slice(new_plaintext);
+
+        let position = self.position;
+        self.seek(offset as u64);
+        let applied = self.apply_keystream(region);
+        self.seek(position);
+
+        applied
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ctr<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        self.apply_keystream(data)
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        self.apply_keystream(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aes::Aes, chal1::hex_to_bytes, chal6::base64_to_bytes};
+
+    #[test]
+    fn test_cryptopals_layout() {
+        let cipher = "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==";
+        let cipher_bytes = base64_to_bytes(cipher).unwrap();
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+
+        let mut ctr =
+            Ctr::with_layout(aes, &[0; 8], CounterLayout::NONCE_64_COUNTER_64_LE).unwrap();
+        let plaintext = ctr.decrypt(&cipher_bytes).unwrap();
+
+        assert_eq!(
+            plaintext,
+            b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+    }
+
+    #[test]
+    fn test_96_32_layout() {
+        // RFC 3686 test vector #2: 32-bit nonce and 64-bit IV, counter from 1.
+        let key = hex_to_bytes("7e24067817fae0d743d6ce1f32539163").unwrap();
+        let nonce = hex_to_bytes("006cb6dbc0543b59da48d90b").unwrap();
+        let plaintext: Vec<u8> = (0..32).collect();
+        let expected =
+            hex_to_bytes("5104a106168a72d9790d41ee8edad388eb2e1efc46da57c8fce630df9141be28")
+                .unwrap();
+
+        let aes = Aes::new(&key).unwrap();
+        let mut ctr = Ctr::with_layout(aes, &nonce, CounterLayout::NONCE_96_COUNTER_32_BE).unwrap();
+        ctr.set_initial_counter(1);
+
+        assert_eq!(ctr.encrypt(&plaintext).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_seek_and_edit() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let layout = CounterLayout::NONCE_64_COUNTER_64_LE;
+        let plaintext: Vec<u8> = (0..100).collect();
+        let ciphertext = Ctr::with_layout(&aes, &[7; 8], layout)
+            .unwrap()
+            .encrypt(&plaintext)
+            .unwrap();
+
+        //-------------------- seek to any byte offset --------------------//
+
+        let mut ctr = Ctr::with_layout(&aes, &[7; 8], layout).unwrap();
+        for offset in [0, 1, 15, 16, 17, 50, 99] {
+            ctr.seek(offset as u64);
+            assert_eq!(
+                ctr.decrypt(&ciphertext[offset..]).unwrap(),
+                plaintext[offset..]
+            );
+            assert_eq!(ctr.position(), 100);
+        }
+
+        let keystream = ctr.keystream_at(30, 20).unwrap();
+        let expected: Vec<u8> = (30..50).map(|i| ciphertext[i] ^ plaintext[i]).collect();
+        assert_eq!(keystream, expected);
+
+        //---------------------- edit a region in place ----------------------//
+
+        let mut edited = ciphertext.clone();
+        ctr.edit(&mut edited, 20, b"edited region").unwrap();
+
+        assert_eq!(edited[..20], ciphertext[..20]);
+        assert_eq!(edited[33..], ciphertext[33..]);
+        assert_eq!(ctr.position(), 100);
+
+        ctr.seek(0);
+        let decrypted = ctr.decrypt(&edited).unwrap();
+        assert_eq!(&decrypted[20..33], b"edited region");
+
+        assert_eq!(
+            ctr.edit(&mut edited, 95, b"too long"),
+            Err("Edited region should lie inside the ciphertext")
+        );
+
+        //------------------ the end of the keystream ------------------//
+
+        ctr.seek(u64::MAX - 4);
+        assert!(ctr.keystream_at(u64::MAX - 4, 4).is_ok());
+        let mut data = [0u8; 5];
+        assert_eq!(
+            ctr.apply_keystream(&mut data),
+            Err("The keystream position would wrap around")
+        );
+        assert_eq!(data, [0; 5]);
+        assert_eq!(ctr.position(), u64::MAX - 4);
+        assert!(ctr.encrypt(&[0; 5]).is_err());
+    }
+
+    #[test]
+    fn test_counter_wraps_around() {
+        let layout = CounterLayout {
+            nonce_len: 14,
+            counter_len: 2,
+            counter_endianness: Endianness::Big,
+        };
+
+        // the counter wraps inside its own width and leaves the nonce alone.
+        let block = layout.counter_block(&[0xaa; 14], 0xffff + 3).unwrap();
+        assert_eq!(block[..14], [0xaa; 14]);
+        assert_eq!(block[14..], [0x00, 0x02]);
+
+        // a full-block counter wraps around the whole block.
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut ctr = Ctr::new(&aes, &[0xff; 16]).unwrap();
+        let keystream = ctr.keystream_at(0, 32).unwrap();
+
+        let mut expected = [0xff; 16];
+        aes.encrypt_block(&mut expected);
+        let mut second = [0x00; 16];
+        aes.encrypt_block(&mut second);
+        assert_eq!(keystream[..16], expected);
+        assert_eq!(keystream[16..], second);
+
+        //-------------------------- invalid layouts --------------------------//
+
+        assert_eq!(
+            Ctr::with_layout(&aes, &[0; 12], CounterLayout::NONCE_64_COUNTER_64_LE).err(),
+            Some("Nonce length does not match the counter layout")
+        );
+        assert_eq!(
+            Ctr::with_layout(&aes, &[0; 8], CounterLayout::full_block(8)).err(),
+            Some("Nonce and counter widths should add up to the block size")
+        );
+
+        let wide = CounterLayout {
+            nonce_len: 0,
+            counter_len: 17,
+            counter_endianness: Endianness::Big,
+        };
+        assert!(wide.counter_block(&[], 0).is_err());
+        assert!(CounterLayout::NONCE_64_COUNTER_64_LE
+            .counter_block(&[0; 4], 0)
+            .is_err());
+    }
+
+    /// A toy cipher with 32 byte blocks: rotates the block and flips bits.
+    struct Wide;
+
+    impl BlockCipher for Wide {
+        fn block_size(&self) -> usize {
+            32
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) {
+            block.rotate_left(1);
+            block.iter_mut().for_each(|byte| *byte ^= 0x5c);
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) {
+            block.iter_mut().for_each(|byte| *byte ^= 0x5c);
+            block.rotate_right(1);
+        }
+    }
+
+    #[test]
+    fn test_wide_block() {
+        // the low 16 bytes count and wrap around, the high ones stay fixed.
+        let mut initial = [0xaa; 32];
+        initial[16..].copy_from_slice(&[0xff; 16]);
+        let mut ctr = Ctr::new(Wide, &initial).unwrap();
+        let keystream = ctr.keystream_at(0, 64).unwrap();
+
+        let mut first = initial;
+        Wide.encrypt_block(&mut first);
+        let mut second = [0xaa; 32];
+        second[16..].copy_from_slice(&[0; 16]);
+        Wide.encrypt_block(&mut second);
+        assert_eq!(keystream[..32], first);
+        assert_eq!(keystream[32..], second);
+
+        let plaintext: Vec<u8> = (0..100).collect();
+        let ciphertext = Ctr::new(Wide, &initial)
+            .unwrap()
+            .encrypt(&plaintext)
+            .unwrap();
+        let mut ctr = Ctr::new(Wide, &initial).unwrap();
+        assert_eq!(ctr.decrypt(&ciphertext).unwrap(), plaintext);
+
+        assert_eq!(
+            Ctr::new(Wide, &[0; 16]).err(),
+            Some("IV length should be equal to the block size")
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! CBC with ciphertext stealing (NIST SP 800-38A addendum)
+//!
+//! Ciphertext stealing lets CBC handle messages of any length of at least one
+//! block without padding, so the ciphertext is exactly as long as the
+//! plaintext. The last partial block is zero padded and encrypted as usual,
+//! and the ciphertext block before it is cut down to the length of the
+//! partial block, since the missing bytes can be recovered from the last
+//! block. The three variants only differ in the order of the last two blocks.
+
+use crate::{
+    chal2::xor_in_place,
+    modes::{BlockCipher, BlockMode, Cbc},
+};
+
+/// Order of the last two ciphertext blocks. With `n` the last block and `n-1`
+/// the cut down block before it:
+/// * `Cs1` always writes `n-1` first.
+/// * `Cs2` writes `n` first, unless the message is a whole number of blocks,
+///   when it is plain CBC.
+/// * `Cs3` always writes `n` first, even for whole blocks. This is the variant
+///   Kerberos uses (RFC 3962).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtsVariant {
+    Cs1,
+    Cs2,
+    Cs3,
+}
+
+/// CBC with ciphertext stealing. Unlike the modes in `modes`, every call
+/// processes a whole message with the IV given at construction, since the
+/// last two blocks can only be handled once the end of the message is known.
+///
+/// # Examples
+/// ```
+/// use set1::{aes::Aes, cts::{CbcCts, CtsVariant}};
+///
+/// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+/// let cts = CbcCts::new(aes, &[0; 16], CtsVariant::Cs3).unwrap();
+///
+/// let ciphertext = cts.encrypt(b"ciphertext stealing").unwrap();
+/// assert_eq!(ciphertext.len(), 19);
+/// assert_eq!(cts.decrypt(&ciphertext).unwrap(), b"ciphertext stealing");
+/// ```
+pub struct CbcCts<C> {
+    cipher: C,
+    iv: Vec<u8>,
+    variant: CtsVariant,
+}
+
+impl<C: BlockCipher> CbcCts<C> {
+    /// Returns an error if the IV is not one block long.
+    pub fn new(cipher: C, iv: &[u8], variant: CtsVariant) -> Result<Self, &'static str> {
+        if iv.len() != cipher.block_size() {
+            return Err("IV length should be equal to the block size");
+        }
+
+        Ok(CbcCts {
+            cipher,
+            iv: iv.to_vec(),
+            variant,
+        })
+    }
+
+    /// Encrypts a message of at least one block. The ciphertext has the same
+    /// length as the plaintext.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let block_size = self.cipher.block_size();
+        let partial = self.last_block_len(plaintext.len())?;
+
+        // CBC over the zero padded message.
+        let mut ciphertext = plaintext.to_vec();
+        ciphertext.resize(
+            (plaintext.len() + block_size - 1) / block_size * block_size,
+            0,
+        );
+        Cbc::new(&self.cipher, &self.iv)?.encrypt_in_place(&mut ciphertext)?;
+        if ciphertext.len() == block_size {
+            return Ok(ciphertext);
+        }
+
+        let tail = ciphertext.split_off(ciphertext.len() - 2 * block_size);
+        let (previous, last) = tail.split_at(block_size);
+        let previous = &previous[..partial];
+
+        if self.last_block_first(partial == block_size) {
+            ciphertext.extend_from_slice(last);
+            ciphertext.extend_from_slice(previous);
+        } else {
+            ciphertext.extend_from_slice(previous);
+            ciphertext.extend_from_slice(last);
+        }
+
+        Ok(ciphertext)
+    }
+
+    /// Decrypts a message of at least one block.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let block_size = self.cipher.block_size();
+        let partial = self.last_block_len(ciphertext.len())?;
+        if ciphertext.len() == block_size {
+            return Cbc::new(&self.cipher, &self.iv)?.decrypt(ciphertext);
+        }
+
+        let (head, tail) = ciphertext.split_at(ciphertext.len() - block_size - partial);
+        let (previous, last) = if self.last_block_first(partial == block_size) {
+            let (last, previous) = tail.split_at(block_size);
+            (previous, last)
+        } else {
+            tail.split_at(partial)
+        };
+
+        // decrypting the last block gives the whole previous ciphertext block
+        // XORed with the zero padded last plaintext block, so the stolen bytes
+        // come out as they are.
+        let mut last_plaintext = last.to_vec();
+        self.cipher.decrypt_block(&mut last_plaintext);
+
+        let mut blocks = head.to_vec();
+        blocks.extend_from_slice(previous);
+        blocks.extend_from_slice(&last_plaintext[partial..]);
+
+        last_plaintext.truncate(partial);
+        xor_in_place(&mut last_plaintext, previous);
+
+        let mut plaintext = Cbc::new(&self.cipher, &self.iv)?.decrypt(&blocks)?;
+        plaintext.extend_from_slice(&last_plaintext);
+
+        Ok(plaintext)
+    }
+
+    /// Returns the length of the last, possibly partial, block.
+    fn last_block_len(&self, len: usize) -> Result<usize, &'static str> {
+        let block_size = self.cipher.block_size();
+        if len < block_size {
+            return Err("Ciphertext stealing needs at least one full block");
+        }
+
+        Ok(match len % block_size {
+            0 => block_size,
+            partial => partial,
+        })
+    }
+
+    fn last_block_first(&self, whole_blocks: bool) -> bool {
+        match self.variant {
+            CtsVariant::Cs1 => false,
+            CtsVariant::Cs2 => !whole_blocks,
+            CtsVariant::Cs3 => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aes::Aes, chal1::hex_to_bytes};
+
+    /// Checks `(length, ciphertext)` pairs for prefixes of the RFC 3962
+    /// message, with the key "chicken teriyaki" and a zero IV.
+    fn check_vectors(variant: CtsVariant, vectors: &[(usize, &str)]) {
+        let aes = Aes::new(b"chicken teriyaki").unwrap();
+        let cts = CbcCts::new(&aes, &[0; 16], variant).unwrap();
+        let plaintext = b"I would like the General Gau's Chicken, please, and wonton soup.";
+
+        for &(len, ciphertext) in vectors {
+            let ciphertext = hex_to_bytes(ciphertext).unwrap();
+
+            assert_eq!(cts.encrypt(&plaintext[..len]).unwrap(), ciphertext);
+            assert_eq!(cts.decrypt(&ciphertext).unwrap(), plaintext[..len]);
+        }
+    }
+
+    #[test]
+    fn test_rfc_3962_vectors() {
+        // CS3 is the Kerberos variant.
+        let vectors = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+            (
+                47,
+                "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+                 39312523a78662d5be7fcbcc98ebf5",
+            ),
+            (
+                48,
+                "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+                 39312523a78662d5be7fcbcc98ebf5a8",
+            ),
+            (
+                64,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+            ),
+        ];
+        check_vectors(CtsVariant::Cs3, &vectors);
+    }
+
+    #[test]
+    fn test_cs1_cs2_vectors() {
+        // the addendum has no vectors of its own. These are the RFC 3962
+        // ciphertexts with the last two blocks in the order of each variant,
+        // so whole blocks are the plain CBC ciphertext.
+        let cs1 = [
+            (17, "97c6353568f2bf8cb4d8a580362da7ff7f"),
+            (
+                31,
+                "97687268d6ecccc0c07b25e25ecfe5fc00783e0efdb2c1d445d4c8eff7ed22",
+            ),
+            (
+                32,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+            ),
+            (
+                47,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5\
+                 b3fffd940c16a18c1b5549d2f838029e",
+            ),
+            (
+                48,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 9dad8bbb96c4cdc03bc103e1a194bbd8",
+            ),
+            (
+                64,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+            ),
+        ];
+        check_vectors(CtsVariant::Cs1, &cs1);
+
+        let cs2 = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+            ),
+            (
+                47,
+                "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+                 39312523a78662d5be7fcbcc98ebf5",
+            ),
+            (
+                48,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 9dad8bbb96c4cdc03bc103e1a194bbd8",
+            ),
+            (
+                64,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+            ),
+        ];
+        check_vectors(CtsVariant::Cs2, &cs2);
+    }
+
+    #[test]
+    fn test_variants() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = [3u8; 16];
+        let cs1 = CbcCts::new(&aes, &iv, CtsVariant::Cs1).unwrap();
+        let cs2 = CbcCts::new(&aes, &iv, CtsVariant::Cs2).unwrap();
+        let cs3 = CbcCts::new(&aes, &iv, CtsVariant::Cs3).unwrap();
+
+        for len in 16..80 {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let c1 = cs1.encrypt(&plaintext).unwrap();
+            let c2 = cs2.encrypt(&plaintext).unwrap();
+            let c3 = cs3.encrypt(&plaintext).unwrap();
+
+            assert_eq!(c1.len(), len);
+            assert_eq!(cs1.decrypt(&c1).unwrap(), plaintext);
+            assert_eq!(cs2.decrypt(&c2).unwrap(), plaintext);
+            assert_eq!(cs3.decrypt(&c3).unwrap(), plaintext);
+
+            // whole blocks: CS1 and CS2 are plain CBC, CS3 swaps the last two.
+            if len % 16 == 0 {
+                let cbc = Cbc::new(&aes, &iv).unwrap().encrypt(&plaintext).unwrap();
+                assert_eq!(c1, cbc);
+                assert_eq!(c2, cbc);
+                if len > 16 {
+                    assert_eq!(c3[len - 32..len - 16], cbc[len - 16..]);
+                }
+            } else {
+                assert_eq!(c2, c3);
+            }
+
+            // all three agree up to the last two blocks.
+            let head = ((len - 1) / 16 * 16).saturating_sub(16);
+            assert_eq!(c1[..head], c2[..head]);
+        }
+    }
+
+    #[test]
+    fn test_cts_errors() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let cts = CbcCts::new(&aes, &[0; 16], CtsVariant::Cs1).unwrap();
+
+        assert_eq!(
+            cts.encrypt(b"too short"),
+            Err("Ciphertext stealing needs at least one full block")
+        );
+        assert_eq!(
+            cts.decrypt(b"too short"),
+            Err("Ciphertext stealing needs at least one full block")
+        );
+        assert!(CbcCts::new(&aes, &[0; 8], CtsVariant::Cs1).is_err());
+    }
+}
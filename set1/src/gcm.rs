@@ -0,0 +1,360 @@
+//! GHASH and AES-GCM authenticated encryption (NIST SP 800-38D)
+
+use crate::{
+    aes::{Aes, BLOCK_SIZE},
+    ctr::{CounterLayout, Ctr},
+    gf128::Gf128,
+};
+
+/// Computes GHASH of the additional data and the ciphertext under the hash key
+/// `h`. Both inputs are zero-padded to whole blocks, followed by a block with
+/// their lengths in bits, and the blocks are evaluated as a polynomial in `h`:
+/// `X_i = (X_{i-1} + B_i) * h`.
+///
+/// # Examples
+/// ```
+/// use set1::gcm::ghash;
+/// use set1::gf128::Gf128;
+///
+/// // hashing nothing gives zero, whatever the key.
+/// assert_eq!(ghash(Gf128(0x1234), &[], &[]), Gf128::ZERO);
+/// ```
+pub fn ghash(h: Gf128, aad: &[u8], ciphertext: &[u8]) -> Gf128 {
+    let mut hash = Gf128::ZERO;
+    for block in aad.chunks(BLOCK_SIZE).chain(ciphertext.chunks(BLOCK_SIZE)) {
+        // `chunks` never gives more than a block.
+        hash = (hash + Gf128::from_partial_block(block).unwrap()) * h;
+    }
+
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    (hash + Gf128(lengths)) * h
+}
+
+/// AES in Galois/counter mode. The plaintext is encrypted in counter mode and
+/// the additional data and the ciphertext are authenticated with GHASH under
+/// the hash key `H = E_K(0^128)`.
+///
+/// # Examples
+/// ```
+/// use set1::gcm::AesGcm;
+///
+/// let gcm = AesGcm::new(b"YELLOW SUBMARINE").unwrap();
+/// let (ciphertext, tag) = gcm.encrypt(b"unique nonce", b"header", b"secret").unwrap();
+///
+/// let plaintext = gcm.decrypt(b"unique nonce", b"header", &ciphertext, &tag).unwrap();
+/// assert_eq!(plaintext, b"secret");
+///
+/// // tampering with the additional data is detected.
+/// assert!(gcm.decrypt(b"unique nonce", b"Header", &ciphertext, &tag).is_err());
+/// ```
+pub struct AesGcm {
+    aes: Aes,
+    h: Gf128,
+    tag_len: usize,
+}
+
+impl AesGcm {
+    /// Creates the cipher with full 16 byte tags. The key can be 16, 24 or 32
+    /// bytes long.
+    pub fn new(key: &[u8]) -> Result<Self, &'static str> {
+        AesGcm::with_tag_len(key, BLOCK_SIZE)
+    }
+
+    /// Creates the cipher with truncated tags. SP 800-38D allows 4, 8 and 12 to
+    /// 16 bytes; short tags are exactly what the forgery attacks go after.
+    pub fn with_tag_len(key: &[u8], tag_len: usize) -> Result<Self, &'static str> {
+        if !matches!(tag_len, 4 | 8 | 12..=16) {
+            return Err("Tag length should be 4, 8 or between 12 and 16 bytes");
+        }
+
+        let aes = Aes::new(key)?;
+        let mut h = [0u8; BLOCK_SIZE];
+        aes.encrypt_block(&mut h);
+
+        Ok(AesGcm {
+            aes,
+            h: Gf128::from_bytes(&h),
+            tag_len,
+        })
+    }
+
+    /// Returns the GHASH key `H`.
+    pub fn hash_key(&self) -> Gf128 {
+        self.h
+    }
+
+    pub fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    /// Derives the pre-counter block J0. A 96-bit IV is used directly with the
+    /// counter set to one; any other length is hashed with GHASH.
+    fn pre_counter_block(&self, iv: &[u8]) -> Result<[u8; BLOCK_SIZE], &'static str> {
+        if iv.is_empty() {
+            return Err("IV should not be empty");
+        }
+
+        if iv.len() == 12 {
+            let mut j0 = [0u8; BLOCK_SIZE];
+            j0[..12].copy_from_slice(iv);
+            j0[15] = 1;
+            return Ok(j0);
+        }
+
+        Ok(ghash(self.h, &[], iv).to_bytes())
+    }
+
+    /// Runs counter mode from `inc32(J0)`: the first 96 bits of J0 stay fixed
+    /// and the last 32 bits count up as a big-endian integer.
+    fn gctr(&self, j0: &[u8; BLOCK_SIZE], data: &mut [u8]) {
+        let mut ctr = Ctr::with_layout(&self.aes, &j0[..12], CounterLayout::NONCE_96_COUNTER_32_BE)
+            .expect("the GCM layout matches the AES block size");
+        let counter = u32::from_be_bytes(j0[12..].try_into().unwrap());
+        ctr.set_initial_counter(counter as u128 + 1);
+        // the keystream starts at offset zero, so it can't run past the end.
+        ctr.apply_keystream(data).unwrap();
+    }
+
+    /// Computes the (possibly truncated) tag `E_K(J0) + GHASH(A, C)`.
+    fn tag(&self, j0: &[u8; BLOCK_SIZE], aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let mut mask = *j0;
+        self.aes.encrypt_block(&mut mask);
+
+        let tag = Gf128::from_bytes(&mask) + ghash(self.h, aad, ciphertext);
+        tag.to_bytes()[..self.tag_len].to_vec()
+    }
+
+    /// Encrypts `plaintext` and authenticates it together with `aad`. Returns
+    /// the ciphertext and the tag. The IV can have any non-zero length, but it
+    /// must never be reused with the same key.
+    pub fn encrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), &'static str> {
+        let j0 = self.pre_counter_block(iv)?;
+        let mut ciphertext = plaintext.to_vec();
+        self.gctr(&j0, &mut ciphertext);
+        let tag = self.tag(&j0, aad, &ciphertext);
+
+        Ok((ciphertext, tag))
+    }
+
+    /// Checks the tag and decrypts `ciphertext`. Nothing is decrypted unless
+    /// the tag matches.
+    pub fn decrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, &'static str> {
+        let j0 = self.pre_counter_block(iv)?;
+        let expected = self.tag(&j0, aad, ciphertext);
+
+        // compare every byte so the time taken does not depend on where the
+        // first difference is.
+        let difference = expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if tag.len() != expected.len() || difference != 0 {
+            return Err("Authentication tag mismatch");
+        }
+
+        let mut plaintext = ciphertext.to_vec();
+        self.gctr(&j0, &mut plaintext);
+
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a\
+                             86a7a9531534f7da2e4c303d8a318a72\
+                             1c3c0c95956809532fcf0e2449a6b525\
+                             b16aedf5aa0de657ba637b391aafd255";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    /// Runs one known answer test in both directions.
+    fn check_vector(key: &str, iv: &str, aad: &str, plaintext: &str, ciphertext: &str, tag: &str) {
+        let gcm = AesGcm::new(&hex_to_bytes(key).unwrap()).unwrap();
+        let iv = hex_to_bytes(iv).unwrap();
+        let aad = hex_to_bytes(aad).unwrap();
+        let plaintext = hex_to_bytes(plaintext).unwrap();
+        let ciphertext = hex_to_bytes(ciphertext).unwrap();
+        let tag = hex_to_bytes(tag).unwrap();
+
+        assert_eq!(
+            gcm.encrypt(&iv, &aad, &plaintext).unwrap(),
+            (ciphertext.clone(), tag.clone())
+        );
+        assert_eq!(
+            gcm.decrypt(&iv, &aad, &ciphertext, &tag).unwrap(),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn test_gcm_vectors() {
+        // test cases 1 to 6 and 15 of the GCM specification, as used in the
+        // NIST validation suite.
+
+        //------------------------ test case 1: empty ------------------------//
+
+        check_vector(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        );
+
+        //----------------------- test case 2: one block -----------------------//
+
+        check_vector(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "00000000000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        );
+
+        //---------------------- test case 3: four blocks ----------------------//
+
+        let ciphertext = "42831ec2217774244b7221b784d0d49c\
+                          e3aa212f2c02a4e035c17e2329aca12e\
+                          21d514b25466931c7d8f6a5aac84aa05\
+                          1ba30b396a0aac973d58e091473f5985";
+        check_vector(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            "",
+            PLAINTEXT,
+            ciphertext,
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        );
+
+        //----------------- test case 4: partial block and AAD -----------------//
+
+        check_vector(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            AAD,
+            &PLAINTEXT[..120],
+            &ciphertext[..120],
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        );
+
+        //--------------------- test case 5: 64-bit IV ---------------------//
+
+        check_vector(
+            KEY,
+            "cafebabefacedbad",
+            AAD,
+            &PLAINTEXT[..120],
+            "61353b4c2806934a777ff51fa22a4755\
+             699b2a714fcdc6f83766e5f97b6c7423\
+             73806900e49f24b22b097544d4896b42\
+             4989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        );
+
+        //--------------------- test case 6: 480-bit IV ---------------------//
+
+        check_vector(
+            KEY,
+            "9313225df88406e555909c5aff5269aa\
+             6a7a9538534f7da1e4c303d2a318a728\
+             c3c0c95156809539fcf0e2429a6b5254\
+             16aedbf5a0de6a57a637b39b",
+            AAD,
+            &PLAINTEXT[..120],
+            "8ce24998625615b603a033aca13fb894\
+             be9112a5c3a211a8ba262a3cca7e2ca7\
+             01e4a9a4fba43c90ccdcb281d48c7c6f\
+             d62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        );
+
+        //----------------------- test case 15: AES-256 -----------------------//
+
+        check_vector(
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "",
+            PLAINTEXT,
+            "522dc1f099567d07f47f37a32a84427d\
+             643a8cdcbfe5c0c97598a2bd2555d1aa\
+             8cb08e48590dbb3da7b08b1056828838\
+             c5f61e6393ba7a0abcc9f662898015ad",
+            "b094dac5d93471bdec1a502270e3cc6c",
+        );
+    }
+
+    #[test]
+    fn test_ghash() {
+        // test case 2 of the GCM specification.
+        let gcm = AesGcm::new(&[0; 16]).unwrap();
+        let ciphertext = hex_to_bytes("0388dace60b6a392f328c2b971b2fe78").unwrap();
+
+        assert_eq!(
+            gcm.hash_key().to_bytes().to_vec(),
+            hex_to_bytes("66e94bd4ef8a2c3b884cfa59ca342b2e").unwrap()
+        );
+        assert_eq!(
+            ghash(gcm.hash_key(), &[], &ciphertext).to_bytes().to_vec(),
+            hex_to_bytes("f38cbb1ad69223dcc3457ae5b6b0f885").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_truncated_tags() {
+        let key = hex_to_bytes(KEY).unwrap();
+        let iv = hex_to_bytes("cafebabefacedbaddecaf888").unwrap();
+        let aad = hex_to_bytes(AAD).unwrap();
+        let plaintext = hex_to_bytes(PLAINTEXT).unwrap();
+
+        // a truncated tag is a prefix of the full tag.
+        let (ciphertext, full_tag) = AesGcm::new(&key)
+            .unwrap()
+            .encrypt(&iv, &aad, &plaintext)
+            .unwrap();
+        let gcm = AesGcm::with_tag_len(&key, 8).unwrap();
+        let (_, tag) = gcm.encrypt(&iv, &aad, &plaintext).unwrap();
+        assert_eq!(tag, full_tag[..8]);
+        assert_eq!(
+            gcm.decrypt(&iv, &aad, &ciphertext, &tag).unwrap(),
+            plaintext
+        );
+
+        //----------------------------- rejections -----------------------------//
+
+        let mut forged = ciphertext.clone();
+        forged[0] ^= 1;
+        assert_eq!(
+            gcm.decrypt(&iv, &aad, &forged, &tag),
+            Err("Authentication tag mismatch")
+        );
+        assert_eq!(
+            gcm.decrypt(&iv, &aad, &ciphertext, &full_tag),
+            Err("Authentication tag mismatch")
+        );
+        assert_eq!(
+            AesGcm::with_tag_len(&key, 6).err(),
+            Some("Tag length should be 4, 8 or between 12 and 16 bytes")
+        );
+        assert_eq!(
+            gcm.encrypt(&[], &aad, &plaintext),
+            Err("IV should not be empty")
+        );
+    }
+}
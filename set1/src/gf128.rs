@@ -0,0 +1,451 @@
+//! Arithmetic in GF(2^128) as used by GCM, and polynomials over it
+
+use std::ops::{Add, Mul, Sub};
+
+/// The GCM reduction polynomial x^128 + x^7 + x^2 + x + 1, without the x^128
+/// term, in GCM bit order.
+const R: u128 = 0xe1 << 120;
+
+/// An element of GF(2^128) in the GCM representation. A 16 byte block is read
+/// as a big-endian integer and its most significant bit is the coefficient of
+/// x^0, so the bit order is reflected compared to the usual notation.
+///
+/// Addition and subtraction are both XOR.
+///
+/// # Examples
+/// ```
+/// use set1::gf128::Gf128;
+///
+/// let h = Gf128::from_bytes(&[0x66; 16]);
+/// assert_eq!(h * Gf128::ONE, h);
+/// assert_eq!(h * h.inverse(), Gf128::ONE);
+/// assert_eq!(h + h, Gf128::ZERO);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Gf128(pub u128);
+
+impl Gf128 {
+    pub const ZERO: Gf128 = Gf128(0);
+    pub const ONE: Gf128 = Gf128(1 << 127);
+
+    /// Reads an element from a 16 byte block.
+    pub fn from_bytes(bytes: &[u8; 16]) -> Gf128 {
+        Gf128(u128::from_be_bytes(*bytes))
+    }
+
+    /// Reads an element from up to 16 bytes, padding with zeroes on the right.
+    /// Returns an error for more than 16 bytes.
+    pub fn from_partial_block(bytes: &[u8]) -> Result<Gf128, &'static str> {
+        if bytes.len() > 16 {
+            return Err("A block should be at most 16 bytes long");
+        }
+
+        let mut block = [0u8; 16];
+        block[..bytes.len()].copy_from_slice(bytes);
+        Ok(Gf128::from_bytes(&block))
+    }
+
+    /// Writes the element as a 16 byte block.
+    pub fn to_bytes(self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    /// Raises the element to the power `exponent` by square-and-multiply.
+    pub fn pow(self, mut exponent: u128) -> Gf128 {
+        let mut result = Gf128::ONE;
+        let mut base = self;
+        while exponent != 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    /// Returns the multiplicative inverse, computed as `self^(2^128 - 2)`. The
+    /// inverse of zero is defined as zero.
+    pub fn inverse(self) -> Gf128 {
+        self.pow(u128::MAX - 1)
+    }
+}
+
+impl Add for Gf128 {
+    type Output = Gf128;
+
+    // addition of binary polynomials is XOR.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Gf128) -> Gf128 {
+        Gf128(self.0 ^ other.0)
+    }
+}
+
+impl Sub for Gf128 {
+    type Output = Gf128;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: Gf128) -> Gf128 {
+        Gf128(self.0 ^ other.0)
+    }
+}
+
+impl Mul for Gf128 {
+    type Output = Gf128;
+
+    /// Multiplication from NIST SP 800-38D, algorithm 1. Every bit of `self`
+    /// is checked from x^0 upwards, and `other` is multiplied by x at every
+    /// step, reducing by the GCM polynomial when the x^127 coefficient falls
+    /// off. The loop runs the same way for every input.
+    fn mul(self, other: Gf128) -> Gf128 {
+        let mut product = 0u128;
+        let mut v = other.0;
+
+        for i in (0..128).rev() {
+            // add `v` when bit i is set, without branching on the data.
+            let bit = (self.0 >> i) & 1;
+            product ^= v & bit.wrapping_neg();
+
+            // multiply `v` by x.
+            let carry = v & 1;
+            v = (v >> 1) ^ (R & carry.wrapping_neg());
+        }
+
+        Gf128(product)
+    }
+}
+
+/// A polynomial with coefficients in GF(2^128), stored from the constant term
+/// upwards. Trailing zero coefficients are always trimmed, so the zero
+/// polynomial has no coefficients.
+///
+/// These are the building blocks for the GCM nonce-reuse attack: with two
+/// messages under the same key and nonce, the authentication key is a root of
+/// the difference of their GHASH polynomials.
+///
+/// # Examples
+/// ```
+/// use set1::gf128::{Gf128, Polynomial};
+///
+/// let a = Gf128(3);
+/// let b = Gf128(5);
+///
+/// // (x - a)(x - b) vanishes at both a and b.
+/// let p = Polynomial::new(vec![a, Gf128::ONE]) * Polynomial::new(vec![b, Gf128::ONE]);
+/// assert_eq!(p.degree(), Some(2));
+/// assert_eq!(p.eval(a), Gf128::ZERO);
+/// assert_eq!(p.eval(b), Gf128::ZERO);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<Gf128>,
+}
+
+impl Polynomial {
+    /// Creates a polynomial from its coefficients, constant term first.
+    pub fn new(coefficients: Vec<Gf128>) -> Polynomial {
+        let mut polynomial = Polynomial { coefficients };
+        polynomial.trim();
+        polynomial
+    }
+
+    /// Returns the zero polynomial.
+    pub fn zero() -> Polynomial {
+        Polynomial::default()
+    }
+
+    /// Returns the monomial `coefficient * x^degree`.
+    pub fn monomial(coefficient: Gf128, degree: usize) -> Polynomial {
+        let mut coefficients = vec![Gf128::ZERO; degree + 1];
+        coefficients[degree] = coefficient;
+        Polynomial::new(coefficients)
+    }
+
+    /// Returns the coefficients, constant term first.
+    pub fn coefficients(&self) -> &[Gf128] {
+        &self.coefficients
+    }
+
+    /// Returns the degree, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Returns the coefficient of the highest power, or zero.
+    pub fn leading_coefficient(&self) -> Gf128 {
+        self.coefficients.last().copied().unwrap_or_default()
+    }
+
+    /// Drops the trailing zero coefficients.
+    fn trim(&mut self) {
+        while self.coefficients.last() == Some(&Gf128::ZERO) {
+            self.coefficients.pop();
+        }
+    }
+
+    /// Evaluates the polynomial at `x` using Horner's rule.
+    pub fn eval(&self, x: Gf128) -> Gf128 {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(Gf128::ZERO, |acc, coefficient| acc * x + *coefficient)
+    }
+
+    /// Multiplies every coefficient by `scalar`.
+    pub fn scale(&self, scalar: Gf128) -> Polynomial {
+        Polynomial::new(self.coefficients.iter().map(|c| *c * scalar).collect())
+    }
+
+    /// Divides by the leading coefficient so that it becomes one.
+    pub fn monic(&self) -> Polynomial {
+        if self.is_zero() {
+            return self.clone();
+        }
+        self.scale(self.leading_coefficient().inverse())
+    }
+
+    /// Returns the formal derivative. In characteristic two the even powers
+    /// vanish and the odd ones lose one degree.
+    pub fn derivative(&self) -> Polynomial {
+        Polynomial::new(
+            self.coefficients
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(i, c)| if i % 2 == 1 { *c } else { Gf128::ZERO })
+                .collect(),
+        )
+    }
+
+    /// Long division. Returns the quotient and the remainder, or an error when
+    /// dividing by the zero polynomial.
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), &'static str> {
+        let divisor_degree = divisor.degree().ok_or("Division by the zero polynomial")?;
+        let lead_inverse = divisor.leading_coefficient().inverse();
+
+        let mut remainder = self.coefficients.clone();
+        let quotient_len = remainder.len().saturating_sub(divisor_degree);
+        let mut quotient = vec![Gf128::ZERO; quotient_len];
+
+        for shift in (0..quotient_len).rev() {
+            let factor = remainder[shift + divisor_degree] * lead_inverse;
+            quotient[shift] = factor;
+            for (i, c) in divisor.coefficients.iter().enumerate() {
+                remainder[shift + i] = remainder[shift + i] - *c * factor;
+            }
+        }
+
+        Ok((Polynomial::new(quotient), Polynomial::new(remainder)))
+    }
+
+    /// Returns the remainder of the division by `modulus`.
+    pub fn rem(&self, modulus: &Polynomial) -> Result<Polynomial, &'static str> {
+        Ok(self.div_rem(modulus)?.1)
+    }
+
+    /// Returns the monic greatest common divisor of two polynomials.
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let remainder = a.rem(&b).unwrap();
+            a = b;
+            b = remainder;
+        }
+
+        a.monic()
+    }
+
+    /// Raises the polynomial to `exponent` modulo `modulus`, for powers too
+    /// large to expand directly. The exponent stops at `2^128 - 1`, so the
+    /// `x^(2^128)` of root finding is `x^(2^127)` squared once more.
+    pub fn pow_mod(
+        &self,
+        mut exponent: u128,
+        modulus: &Polynomial,
+    ) -> Result<Polynomial, &'static str> {
+        let mut result = Polynomial::new(vec![Gf128::ONE]).rem(modulus)?;
+        let mut base = self.rem(modulus)?;
+        while exponent != 0 {
+            if exponent & 1 == 1 {
+                result = (&result * &base).rem(modulus)?;
+            }
+            base = (&base * &base).rem(modulus)?;
+            exponent >>= 1;
+        }
+
+        Ok(result)
+    }
+}
+
+impl Add for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &Polynomial) -> Polynomial {
+        let len = self.coefficients.len().max(other.coefficients.len());
+        let coefficient =
+            |p: &Polynomial, i: usize| p.coefficients.get(i).copied().unwrap_or_default();
+
+        Polynomial::new(
+            (0..len)
+                .map(|i| coefficient(self, i) + coefficient(other, i))
+                .collect(),
+        )
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: Polynomial) -> Polynomial {
+        &self + &other
+    }
+}
+
+impl Sub for &Polynomial {
+    type Output = Polynomial;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: &Polynomial) -> Polynomial {
+        // subtraction is addition in characteristic two.
+        self + other
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: Polynomial) -> Polynomial {
+        &self - &other
+    }
+}
+
+impl Mul for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: &Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return Polynomial::zero();
+        }
+
+        let mut product = vec![Gf128::ZERO; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                product[i + j] = product[i + j] + *a * *b;
+            }
+        }
+
+        Polynomial::new(product)
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: Polynomial) -> Polynomial {
+        &self * &other
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    fn element(hex: &str) -> Gf128 {
+        Gf128::from_bytes(&hex_to_bytes(hex).unwrap().try_into().unwrap())
+    }
+
+    #[test]
+    fn test_multiplication() {
+        let a = element("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let b = element("0388dace60b6a392f328c2b971b2fe78");
+        let c = element("f38cbb1ad69223dcc3457ae5b6b0f885");
+
+        // field axioms.
+        assert_eq!(a * b, b * a);
+        assert_eq!((a * b) * c, a * (b * c));
+        assert_eq!(a * (b + c), a * b + a * c);
+        assert_eq!(a * Gf128::ONE, a);
+        assert_eq!(a * Gf128::ZERO, Gf128::ZERO);
+
+        // multiplying by x shifts one bit towards the low end.
+        let x = Gf128(1 << 126);
+        assert_eq!(Gf128(1 << 100) * x, Gf128(1 << 99));
+
+        // x^127 * x wraps around to the reduction polynomial.
+        assert_eq!(Gf128(1) * x, Gf128(R));
+
+        //-------------------------- inverse and pow --------------------------//
+
+        assert_eq!(a * a.inverse(), Gf128::ONE);
+        assert_eq!(a.pow(3), a * a * a);
+        assert_eq!(Gf128::ZERO.inverse(), Gf128::ZERO);
+
+        //-------------------------- partial blocks --------------------------//
+
+        assert_eq!(Gf128::from_partial_block(&[0x80]).unwrap(), Gf128::ONE);
+        assert_eq!(Gf128::from_partial_block(&[]).unwrap(), Gf128::ZERO);
+        assert!(Gf128::from_partial_block(&[0; 17]).is_err());
+    }
+
+    #[test]
+    fn test_polynomials() {
+        let roots = [
+            Gf128(3),
+            Gf128(0xdead << 64),
+            element("66e94bd4ef8a2c3b884cfa59ca342b2e"),
+        ];
+
+        // build (x - r0)(x - r1)(x - r2).
+        let product = roots
+            .iter()
+            .fold(Polynomial::new(vec![Gf128::ONE]), |acc, root| {
+                acc * Polynomial::new(vec![*root, Gf128::ONE])
+            });
+        assert_eq!(product.degree(), Some(3));
+        assert_eq!(product.leading_coefficient(), Gf128::ONE);
+        for root in roots {
+            assert_eq!(product.eval(root), Gf128::ZERO);
+        }
+
+        //----------------------------- division -----------------------------//
+
+        let divisor = Polynomial::new(vec![roots[0], Gf128::ONE]);
+        let (quotient, remainder) = product.div_rem(&divisor).unwrap();
+        assert!(remainder.is_zero());
+        assert_eq!(&quotient * &divisor, product);
+
+        let other = Polynomial::new(vec![Gf128(7), Gf128(9), Gf128(11), Gf128(13), Gf128(17)]);
+        let (quotient, remainder) = other.div_rem(&product).unwrap();
+        assert_eq!(&(&quotient * &product) + &remainder, other);
+        assert!(remainder.degree() < product.degree());
+
+        assert_eq!(
+            other.div_rem(&Polynomial::zero()),
+            Err("Division by the zero polynomial")
+        );
+
+        //---------------------------- gcd and pow ----------------------------//
+
+        let shared = Polynomial::new(vec![roots[1], Gf128::ONE]);
+        let a = &shared * &Polynomial::new(vec![Gf128(5), Gf128::ONE]);
+        let b = &shared * &Polynomial::new(vec![Gf128(6), Gf128(2), Gf128::ONE]);
+        assert_eq!(a.gcd(&b), shared);
+
+        let x = Polynomial::monomial(Gf128::ONE, 1);
+        assert_eq!(
+            x.pow_mod(5, &product).unwrap(),
+            Polynomial::monomial(Gf128::ONE, 5).rem(&product).unwrap()
+        );
+
+        // x^2 + x has derivative 1.
+        let p = Polynomial::new(vec![Gf128::ZERO, Gf128::ONE, Gf128::ONE]);
+        assert_eq!(p.derivative(), Polynomial::new(vec![Gf128::ONE]));
+    }
+}
@@ -0,0 +1,62 @@
+//! HMAC-SHA1 (RFC 2104)
+
+use crate::sha1::{sha1, Sha1, DIGEST_SIZE, SHA1_BLOCK_SIZE};
+
+/// Returns the HMAC-SHA1 of `message` under `key`. Keys longer than a block
+/// are hashed first, shorter ones are padded with zeroes.
+///
+/// # Examples
+/// ```
+/// use set1::hmac::hmac_sha1;
+///
+/// let mac = hmac_sha1(b"Jefe", b"what do ya want for nothing?");
+/// assert_eq!(mac[..4], [0xef, 0xfc, 0xdf, 0x6a]);
+/// ```
+pub fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut block_key = [0u8; SHA1_BLOCK_SIZE];
+    if key.len() > SHA1_BLOCK_SIZE {
+        block_key[..DIGEST_SIZE].copy_from_slice(&sha1(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha1::new();
+    inner.update(&block_key.map(|byte| byte ^ 0x36));
+    inner.update(message);
+
+    let mut outer = Sha1::new();
+    outer.update(&block_key.map(|byte| byte ^ 0x5c));
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    #[test]
+    fn test_rfc2202_vectors() {
+        let vectors: [(&[u8], &[u8], &str); 3] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+        ];
+
+        for (key, message, mac) in vectors {
+            assert_eq!(hmac_sha1(key, message).to_vec(), hex_to_bytes(mac).unwrap());
+        }
+    }
+}
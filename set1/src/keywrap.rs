@@ -0,0 +1,325 @@
+//! AES Key Wrap (RFC 3394) and Key Wrap with Padding (RFC 5649)
+//!
+//! Key wrapping encrypts key material with a key-encryption key (KEK). The
+//! data is processed in 64-bit halves over six passes, and an integrity check
+//! value in the first half block tells on unwrapping whether the KEK was right
+//! and the blob untouched. Failures are reported as `KeyWrapError`, so callers
+//! can tell a bad blob from a bad argument.
+
+use crate::aes::{Aes, Backend, BLOCK_SIZE};
+use std::fmt;
+
+/// The RFC 3394 default initial value.
+const DEFAULT_IV: [u8; 8] = [0xa6; 8];
+
+/// The first half of the RFC 5649 alternative initial value, followed by the
+/// message length.
+const PADDED_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+/// Why wrapping or unwrapping failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyWrapError {
+    /// The KEK is not a valid AES key.
+    InvalidKey,
+    /// The data has a length the scheme can't handle.
+    InvalidLength,
+    /// The unwrapped integrity check value or padding is wrong: the blob was
+    /// modified or the KEK is not the one it was wrapped with.
+    IntegrityCheckFailed,
+}
+
+impl fmt::Display for KeyWrapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            KeyWrapError::InvalidKey => "Invalid AES key length, it should be 16, 24 or 32 bytes",
+            KeyWrapError::InvalidLength => "Invalid length for the key wrap input",
+            KeyWrapError::IntegrityCheckFailed => "Key wrap integrity check failed",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for KeyWrapError {}
+
+/// Wraps `key_data` with RFC 3394. The key data must be at least two 8 byte
+/// halves and a multiple of 8 bytes; the result is 8 bytes longer.
+///
+/// # Examples
+/// ```
+/// use set1::keywrap::{unwrap_key, wrap_key, KeyWrapError};
+///
+/// let wrapped = wrap_key(b"YELLOW SUBMARINE", b"sixteen byte key").unwrap();
+/// assert_eq!(wrapped.len(), 24);
+/// assert_eq!(unwrap_key(b"YELLOW SUBMARINE", &wrapped).unwrap(), b"sixteen byte key");
+///
+/// // the wrong KEK is caught by the integrity check.
+/// assert_eq!(
+///     unwrap_key(b"PURPLE SUBMARINE", &wrapped),
+///     Err(KeyWrapError::IntegrityCheckFailed)
+/// );
+/// ```
+pub fn wrap_key(kek: &[u8], key_data: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    let aes = new_aes(kek)?;
+    if key_data.len() < 16 || key_data.len() % 8 != 0 {
+        return Err(KeyWrapError::InvalidLength);
+    }
+
+    Ok(wrap(&aes, DEFAULT_IV, key_data))
+}
+
+/// Unwraps an RFC 3394 blob and checks its integrity.
+pub fn unwrap_key(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    let aes = new_aes(kek)?;
+    if wrapped.len() < 24 || wrapped.len() % 8 != 0 {
+        return Err(KeyWrapError::InvalidLength);
+    }
+
+    let (iv, key_data) = unwrap(&aes, wrapped);
+    if iv != DEFAULT_IV {
+        return Err(KeyWrapError::IntegrityCheckFailed);
+    }
+
+    Ok(key_data)
+}
+
+/// Wraps `key_data` of any length from 1 byte with RFC 5649. The data is zero
+/// padded to a multiple of 8 bytes and its length is kept in the initial
+/// value.
+///
+/// # Examples
+/// ```
+/// use set1::keywrap::{unwrap_key_padded, wrap_key_padded};
+///
+/// let wrapped = wrap_key_padded(b"YELLOW SUBMARINE", b"ICE ICE BABY").unwrap();
+/// assert_eq!(wrapped.len(), 24);
+/// assert_eq!(unwrap_key_padded(b"YELLOW SUBMARINE", &wrapped).unwrap(), b"ICE ICE BABY");
+/// ```
+pub fn wrap_key_padded(kek: &[u8], key_data: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    let aes = new_aes(kek)?;
+    if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+        return Err(KeyWrapError::InvalidLength);
+    }
+
+    let mut iv = [0u8; 8];
+    iv[..4].copy_from_slice(&PADDED_IV_PREFIX);
+    iv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+
+    let mut padded = key_data.to_vec();
+    padded.resize((key_data.len() + 8 - 1) / 8 * 8, 0);
+
+    // a single half block is encrypted directly with the initial value.
+    if padded.len() == 8 {
+        let mut block = [0u8; BLOCK_SIZE];
+        block[..8].copy_from_slice(&iv);
+        block[8..].copy_from_slice(&padded);
+        aes.encrypt_block(&mut block);
+        return Ok(block.to_vec());
+    }
+
+    Ok(wrap(&aes, iv, &padded))
+}
+
+/// Unwraps an RFC 5649 blob, checks its integrity and removes the padding.
+pub fn unwrap_key_padded(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    let aes = new_aes(kek)?;
+    if wrapped.len() < 16 || wrapped.len() % 8 != 0 {
+        return Err(KeyWrapError::InvalidLength);
+    }
+
+    let (iv, mut key_data) = if wrapped.len() == 16 {
+        let mut block: [u8; BLOCK_SIZE] = wrapped.try_into().unwrap();
+        aes.decrypt_block(&mut block);
+        (block[..8].try_into().unwrap(), block[8..].to_vec())
+    } else {
+        unwrap(&aes, wrapped)
+    };
+
+    // the length has to point into the last half block, and everything after
+    // it has to be zero.
+    let length = u32::from_be_bytes(iv[4..].try_into().unwrap()) as usize;
+    if iv[..4] != PADDED_IV_PREFIX
+        || length == 0
+        || length > key_data.len()
+        || key_data.len() - length >= 8
+        || key_data[length..].iter().any(|byte| *byte != 0)
+    {
+        return Err(KeyWrapError::IntegrityCheckFailed);
+    }
+
+    key_data.truncate(length);
+    Ok(key_data)
+}
+
+/// Key encryption keys are real keys, so they never go through the table
+/// backend.
+fn new_aes(kek: &[u8]) -> Result<Aes, KeyWrapError> {
+    Aes::with_backend(kek, Backend::detect()).map_err(|_| KeyWrapError::InvalidKey)
+}
+
+/// The wrapping function W of RFC 3394 section 2.2.1, for at least two half
+/// blocks.
+fn wrap(aes: &Aes, iv: [u8; 8], key_data: &[u8]) -> Vec<u8> {
+    let mut a = iv;
+    let mut r: Vec<[u8; 8]> = key_data
+        .chunks_exact(8)
+        .map(|half| half.try_into().unwrap())
+        .collect();
+    let n = r.len() as u64;
+
+    for j in 0..6 {
+        for (i, half) in r.iter_mut().enumerate() {
+            let mut block = [0u8; BLOCK_SIZE];
+            block[..8].copy_from_slice(&a);
+            block[8..].copy_from_slice(half);
+            aes.encrypt_block(&mut block);
+
+            let t = n * j + i as u64 + 1;
+            a = (u64::from_be_bytes(block[..8].try_into().unwrap()) ^ t).to_be_bytes();
+            half.copy_from_slice(&block[8..]);
+        }
+    }
+
+    let mut wrapped = a.to_vec();
+    r.iter().for_each(|half| wrapped.extend_from_slice(half));
+    wrapped
+}
+
+/// The unwrapping function W^-1 of RFC 3394 section 2.2.2. Returns the
+/// recovered initial value and key data, which the caller checks.
+fn unwrap(aes: &Aes, wrapped: &[u8]) -> ([u8; 8], Vec<u8>) {
+    let mut a: [u8; 8] = wrapped[..8].try_into().unwrap();
+    let mut r: Vec<[u8; 8]> = wrapped[8..]
+        .chunks_exact(8)
+        .map(|half| half.try_into().unwrap())
+        .collect();
+    let n = r.len() as u64;
+
+    for j in (0..6).rev() {
+        for (i, half) in r.iter_mut().enumerate().rev() {
+            let t = n * j + i as u64 + 1;
+
+            let mut block = [0u8; BLOCK_SIZE];
+            block[..8].copy_from_slice(&(u64::from_be_bytes(a) ^ t).to_be_bytes());
+            block[8..].copy_from_slice(half);
+            aes.decrypt_block(&mut block);
+
+            a.copy_from_slice(&block[..8]);
+            half.copy_from_slice(&block[8..]);
+        }
+    }
+
+    (a, r.concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    #[test]
+    fn test_rfc_3394_vectors() {
+        let kek = hex_to_bytes("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+            .unwrap();
+        let key_data =
+            hex_to_bytes("00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f")
+                .unwrap();
+
+        // (KEK length, key data length, wrapped) for sections 4.1 to 4.6.
+        let vectors = [
+            (16, 16, "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"),
+            (24, 16, "96778b25ae6ca435f92b5b97c050aed2468ab8a17ad84e5d"),
+            (32, 16, "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7"),
+            (
+                24,
+                24,
+                "031d33264e15d33268f24ec260743edce1c6c7ddee725a936ba814915c6762d2",
+            ),
+            (
+                32,
+                24,
+                "a8f9bc1612c68b3ff6e6f4fbe30e71e4769c8b80a32cb8958cd5d17d6b254da1",
+            ),
+            (
+                32,
+                32,
+                "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21",
+            ),
+        ];
+        for (kek_len, data_len, wrapped) in vectors {
+            let wrapped = hex_to_bytes(wrapped).unwrap();
+
+            assert_eq!(
+                wrap_key(&kek[..kek_len], &key_data[..data_len]).unwrap(),
+                wrapped
+            );
+            assert_eq!(
+                unwrap_key(&kek[..kek_len], &wrapped).unwrap(),
+                key_data[..data_len]
+            );
+        }
+    }
+
+    #[test]
+    fn test_rfc_5649_vectors() {
+        let kek = hex_to_bytes("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+
+        let vectors = [
+            (
+                "c37b7e6492584340bed12207808941155068f738",
+                "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+            ),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ];
+        for (key_data, wrapped) in vectors {
+            let key_data = hex_to_bytes(key_data).unwrap();
+            let wrapped = hex_to_bytes(wrapped).unwrap();
+
+            assert_eq!(wrap_key_padded(&kek, &key_data).unwrap(), wrapped);
+            assert_eq!(unwrap_key_padded(&kek, &wrapped).unwrap(), key_data);
+        }
+    }
+
+    #[test]
+    fn test_integrity_failures() {
+        let kek = b"YELLOW SUBMARINE";
+
+        //---------------------------- RFC 3394 ----------------------------//
+
+        let mut wrapped = wrap_key(kek, &[7; 32]).unwrap();
+        wrapped[20] ^= 1;
+        assert_eq!(
+            unwrap_key(kek, &wrapped),
+            Err(KeyWrapError::IntegrityCheckFailed)
+        );
+
+        //---------------------------- RFC 5649 ----------------------------//
+
+        for len in [5, 20] {
+            let mut wrapped = wrap_key_padded(kek, &vec![7; len]).unwrap();
+            wrapped[3] ^= 1;
+            assert_eq!(
+                unwrap_key_padded(kek, &wrapped),
+                Err(KeyWrapError::IntegrityCheckFailed)
+            );
+        }
+
+        // an RFC 3394 blob is not a valid RFC 5649 one.
+        let wrapped = wrap_key(kek, &[7; 16]).unwrap();
+        assert_eq!(
+            unwrap_key_padded(kek, &wrapped),
+            Err(KeyWrapError::IntegrityCheckFailed)
+        );
+
+        //------------------------- bad arguments --------------------------//
+
+        assert_eq!(wrap_key(b"short", &[0; 16]), Err(KeyWrapError::InvalidKey));
+        assert_eq!(wrap_key(kek, &[0; 12]), Err(KeyWrapError::InvalidLength));
+        assert_eq!(unwrap_key(kek, &[0; 16]), Err(KeyWrapError::InvalidLength));
+        assert_eq!(wrap_key_padded(kek, &[]), Err(KeyWrapError::InvalidLength));
+    }
+
+    #[test]
+    fn test_backend() {
+        assert_ne!(new_aes(&[0; 16]).unwrap().backend(), Backend::Table);
+    }
+}
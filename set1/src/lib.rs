@@ -1,3 +1,5 @@
+pub mod aes;
+mod aes_hw;
 pub mod chal1;
 pub mod chal2;
 pub mod chal3;
@@ -6,3 +8,18 @@ pub mod chal5;
 pub mod chal6;
 pub mod chal7;
 pub mod chal8;
+pub mod cmac;
+pub mod ctr;
+pub mod cts;
+pub mod gcm;
+pub mod gf128;
+pub mod hmac;
+pub mod keywrap;
+pub mod malleability;
+pub mod modes;
+pub mod padding;
+pub mod sha1;
+pub mod siv;
+pub mod square;
+pub mod stream;
+pub mod xts;
@@ -0,0 +1,221 @@
+//! Editing ciphertexts without the key
+//!
+//! In stream modes (CTR, OFB) a plaintext byte is the ciphertext byte XOR a
+//! keystream byte, so XORing `known ^ desired` into the ciphertext turns
+//! `known` into `desired` at the same place. In CBC a plaintext block is the
+//! decrypted ciphertext block XOR the previous ciphertext block, so the delta
+//! goes into the previous block instead, and the plaintext block that one
+//! decrypts to comes out scrambled.
+
+use crate::chal2::{xor_bytes, xor_in_place};
+
+/// How the ciphertext to edit was produced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditMode {
+    Ctr,
+    Ofb,
+    /// CBC with blocks of `block_size` bytes. When `iv_prefixed` is set, the
+    /// ciphertext starts with the IV, which lets the first plaintext block be
+    /// edited without scrambling anything.
+    Cbc {
+        block_size: usize,
+        iv_prefixed: bool,
+    },
+}
+
+/// An edited ciphertext.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edited {
+    pub ciphertext: Vec<u8>,
+    /// For CBC, the index of the plaintext block that now decrypts to
+    /// garbage: the one before the edited block, or `None` when the IV took
+    /// the change.
+    pub scrambled_block: Option<usize>,
+}
+
+/// This function changes `known` plaintext at `offset` into `desired` by
+/// XORing their difference into `ciphertext`. Offsets are plaintext offsets;
+/// with an IV prefixed CBC ciphertext they don't count the IV. A CBC edit
+/// must stay within one block, since the block before it is the one that
+/// changes, and editing the first block needs the IV in front.
+///
+/// # Examples
+/// ```
+/// use set1::malleability::{edit_ciphertext, EditMode};
+///
+/// // CBC with the IV in front: editing the second block scrambles the first.
+/// let ciphertext = [0u8; 48];
+/// let edited = edit_ciphertext(
+///     &ciphertext,
+///     EditMode::Cbc { block_size: 16, iv_prefixed: true },
+///     16,
+///     b"user",
+///     b"root",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(edited.scrambled_block, Some(0));
+/// assert_eq!(&edited.ciphertext[16..20], [b'u' ^ b'r', b's' ^ b'o', b'e' ^ b'o', b'r' ^ b't']);
+/// ```
+pub fn edit_ciphertext(
+    ciphertext: &[u8],
+    mode: EditMode,
+    offset: usize,
+    known: &[u8],
+    desired: &[u8],
+) -> Result<Edited, &'static str> {
+    if known.len() != desired.len() {
+        return Err("Known and desired plaintexts should have the same length");
+    }
+    let delta = xor_bytes(known, desired)?;
+    let end = offset
+        .checked_add(delta.len())
+        .ok_or("The edit should fall inside the plaintext")?;
+
+    let mut ciphertext = ciphertext.to_vec();
+    match mode {
+        EditMode::Ctr | EditMode::Ofb => {
+            if end > ciphertext.len() {
+                return Err("The edit should fall inside the plaintext");
+            }
+            xor_in_place(&mut ciphertext[offset..end], &delta);
+
+            Ok(Edited {
+                ciphertext,
+                scrambled_block: None,
+            })
+        }
+        EditMode::Cbc {
+            block_size,
+            iv_prefixed,
+        } => {
+            if block_size == 0 || ciphertext.len() % block_size != 0 {
+                return Err("Ciphertext length should be a multiple of the block size");
+            }
+            let plaintext_len = if iv_prefixed {
+                ciphertext.len().saturating_sub(block_size)
+            } else {
+                ciphertext.len()
+            };
+            if end > plaintext_len {
+                return Err("The edit should fall inside the plaintext");
+            }
+
+            let block = offset / block_size;
+            if !delta.is_empty() && (end - 1) / block_size != block {
+                return Err("A CBC edit should stay within one block");
+            }
+            if block == 0 && !iv_prefixed {
+                return Err("The first block can only be edited through the IV");
+            }
+
+            // in an IV prefixed ciphertext, the block before plaintext block
+            // `i` is ciphertext block `i`.
+            let start = if iv_prefixed {
+                offset
+            } else {
+                offset - block_size
+            };
+            xor_in_place(&mut ciphertext[start..start + delta.len()], &delta);
+
+            Ok(Edited {
+                ciphertext,
+                scrambled_block: block.checked_sub(1),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aes::Aes,
+        ctr::Ctr,
+        modes::{BlockMode, Cbc, Ofb},
+    };
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+    const IV: [u8; 16] = [7; 16];
+    const PLAINTEXT: &[u8] = b"comment1=cooking%20MCs;userdata=A;admin=false;comment2=bacon!!!!";
+
+    #[test]
+    fn test_stream_edits() {
+        let new_ctr = || Ctr::new(Aes::new(KEY).unwrap(), &IV).unwrap();
+        let new_ofb = || Ofb::new(Aes::new(KEY).unwrap(), &IV).unwrap();
+        let ctr_ciphertext = new_ctr().encrypt(PLAINTEXT).unwrap();
+        let ofb_ciphertext = new_ofb().encrypt(PLAINTEXT).unwrap();
+
+        let offset = 34;
+        let edited = edit_ciphertext(
+            &ctr_ciphertext,
+            EditMode::Ctr,
+            offset,
+            b"admin=false",
+            b"admin=true;",
+        )
+        .unwrap();
+        let plaintext = new_ctr().decrypt(&edited.ciphertext).unwrap();
+        assert_eq!(&plaintext[offset..offset + 11], b"admin=true;");
+        assert_eq!(edited.scrambled_block, None);
+
+        let edited =
+            edit_ciphertext(&ofb_ciphertext, EditMode::Ofb, 0, b"comment1", b"COMMENT1").unwrap();
+        let plaintext = new_ofb().decrypt(&edited.ciphertext).unwrap();
+        assert_eq!(&plaintext[..8], b"COMMENT1");
+        assert_eq!(plaintext[8..], PLAINTEXT[8..]);
+    }
+
+    #[test]
+    fn test_cbc_edits() {
+        let new_cbc = || Cbc::new(Aes::new(KEY).unwrap(), &IV).unwrap();
+        let ciphertext = new_cbc().encrypt(PLAINTEXT).unwrap();
+        let mode = EditMode::Cbc {
+            block_size: 16,
+            iv_prefixed: false,
+        };
+
+        // `admin=false` is at 34..45, inside the third block.
+        let edited =
+            edit_ciphertext(&ciphertext, mode, 34, b"admin=false", b"admin=true;").unwrap();
+        assert_eq!(edited.scrambled_block, Some(1));
+        let plaintext = new_cbc().decrypt(&edited.ciphertext).unwrap();
+        assert_eq!(&plaintext[34..45], b"admin=true;");
+        assert_ne!(plaintext[16..32], PLAINTEXT[16..32]);
+        assert_eq!(plaintext[..16], PLAINTEXT[..16]);
+        assert_eq!(plaintext[48..], PLAINTEXT[48..]);
+
+        // the first block goes through the IV, which scrambles nothing.
+        let mut prefixed = IV.to_vec();
+        prefixed.extend_from_slice(&ciphertext);
+        let mode = EditMode::Cbc {
+            block_size: 16,
+            iv_prefixed: true,
+        };
+        let edited = edit_ciphertext(&prefixed, mode, 0, b"comment1", b"COMMENT1").unwrap();
+        assert_eq!(edited.scrambled_block, None);
+        let (iv, body) = edited.ciphertext.split_at(16);
+        let plaintext = Cbc::new(Aes::new(KEY).unwrap(), iv)
+            .unwrap()
+            .decrypt(body)
+            .unwrap();
+        assert_eq!(&plaintext[..8], b"COMMENT1");
+        assert_eq!(plaintext[8..], PLAINTEXT[8..]);
+    }
+
+    #[test]
+    fn test_edit_errors() {
+        let cbc = EditMode::Cbc {
+            block_size: 16,
+            iv_prefixed: false,
+        };
+
+        assert!(edit_ciphertext(&[0; 32], EditMode::Ctr, 0, b"ab", b"a").is_err());
+        assert!(edit_ciphertext(&[0; 32], EditMode::Ctr, 31, b"ab", b"cd").is_err());
+        assert!(edit_ciphertext(&[0; 32], EditMode::Ctr, usize::MAX, b"a", b"b").is_err());
+        assert!(edit_ciphertext(&[0; 31], cbc, 16, b"a", b"b").is_err());
+        assert!(edit_ciphertext(&[0; 48], cbc, 30, b"abcd", b"efgh").is_err());
+        assert!(edit_ciphertext(&[0; 48], cbc, 0, b"a", b"b").is_err());
+        assert!(edit_ciphertext(&[0; 48], cbc, 47, b"ab", b"cd").is_err());
+    }
+}
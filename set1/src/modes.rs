@@ -0,0 +1,442 @@
+//! Block ciphers and their modes of operation
+
+use crate::chal2::xor_in_place;
+
+// counter mode lives in its own module because of the layout and seeking
+// support, but it is a mode like the others.
+pub use crate::ctr::Ctr;
+
+/// A keyed block cipher that transforms one fixed-size block at a time. Every
+/// mode in this module is generic over this trait, so any cipher that
+/// implements it can be used with all of them.
+pub trait BlockCipher {
+    /// Returns the size of a block in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Encrypts a single block in place. The block must be exactly
+    /// `block_size()` bytes long.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    /// Decrypts a single block in place. The block must be exactly
+    /// `block_size()` bytes long.
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+impl<C: BlockCipher + ?Sized> BlockCipher for &C {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        (**self).encrypt_block(block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        (**self).decrypt_block(block)
+    }
+}
+
+/// A mode of operation built on top of a block cipher. The chaining state is
+/// carried over between calls, so a long message can be processed in several
+/// pieces and gives the same result as processing it in one go. ECB and CBC
+/// only accept whole blocks; the stream modes (CFB, OFB and CTR) accept any
+/// length.
+pub trait BlockMode {
+    /// Returns the block size of the underlying cipher.
+    fn block_size(&self) -> usize;
+
+    /// Encrypts `data` in place.
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str>;
+
+    /// Decrypts `data` in place.
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str>;
+
+    /// Encrypts `data` and returns the ciphertext.
+    fn encrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let mut output = data.to_vec();
+        self.encrypt_in_place(&mut output)?;
+        Ok(output)
+    }
+
+    /// Decrypts `data` and returns the plaintext.
+    fn decrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let mut output = data.to_vec();
+        self.decrypt_in_place(&mut output)?;
+        Ok(output)
+    }
+}
+
+impl<M: BlockMode + ?Sized> BlockMode for &mut M {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        (**self).encrypt_in_place(data)
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        (**self).decrypt_in_place(data)
+    }
+}
+
+/// Returns an error if `data` is not a whole number of blocks.
+fn check_whole_blocks(data: &[u8], block_size: usize) -> Result<(), &'static str> {
+    if data.len() % block_size != 0 {
+        return Err("Data length should be a multiple of the block size");
+    }
+
+    Ok(())
+}
+
+/// Returns an error if the IV is not exactly one block long.
+fn check_iv(iv: &[u8], block_size: usize) -> Result<(), &'static str> {
+    if iv.len() != block_size {
+        return Err("IV length should be equal to the block size");
+    }
+
+    Ok(())
+}
+
+/// Electronic codebook mode: every block is encrypted independently with the
+/// same key, so equal plaintext blocks give equal ciphertext blocks.
+///
+/// # Examples
+/// ```
+/// use set1::{aes::Aes, modes::{BlockMode, Ecb}};
+///
+/// let mut ecb = Ecb::new(Aes::new(b"YELLOW SUBMARINE").unwrap());
+/// let ciphertext = ecb.encrypt(&[b'A'; 32]).unwrap();
+///
+/// assert_eq!(ciphertext[..16], ciphertext[16..]);
+/// ```
+pub struct Ecb<C> {
+    cipher: C,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    /// ECB needs no IV, only the cipher.
+    pub fn new(cipher: C) -> Self {
+        Ecb { cipher }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ecb<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        let block_size = self.block_size();
+        check_whole_blocks(data, block_size)?;
+
+        data.chunks_exact_mut(block_size)
+            .for_each(|block| self.cipher.encrypt_block(block));
+
+        Ok(())
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        let block_size = self.block_size();
+        check_whole_blocks(data, block_size)?;
+
+        data.chunks_exact_mut(block_size)
+            .for_each(|block| self.cipher.decrypt_block(block));
+
+        Ok(())
+    }
+}
+
+/// Cipher block chaining mode: every plaintext block is XORed with the previous
+/// ciphertext block (the IV for the first block) before it is encrypted.
+pub struct Cbc<C> {
+    cipher: C,
+    /// The previous ciphertext block, or the IV before the first block.
+    chain: Vec<u8>,
+}
+
+impl<C: BlockCipher> Cbc<C> {
+    /// Returns an error if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8]) -> Result<Self, &'static str> {
+        check_iv(iv, cipher.block_size())?;
+
+        Ok(Cbc {
+            cipher,
+            chain: iv.to_vec(),
+        })
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cbc<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        let block_size = self.block_size();
+        check_whole_blocks(data, block_size)?;
+
+        for block in data.chunks_exact_mut(block_size) {
+            xor_in_place(block, &self.chain);
+            self.cipher.encrypt_block(block);
+            self.chain.copy_from_slice(block);
+        }
+
+        Ok(())
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        let block_size = self.block_size();
+        check_whole_blocks(data, block_size)?;
+
+        for block in data.chunks_exact_mut(block_size) {
+            let ciphertext = block.to_vec();
+            self.cipher.decrypt_block(block);
+            xor_in_place(block, &self.chain);
+            self.chain = ciphertext;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cipher feedback mode with full-block feedback: the keystream block is the
+/// encryption of the previous ciphertext block (the IV for the first block).
+/// The last block may be partial.
+pub struct Cfb<C> {
+    cipher: C,
+    /// The ciphertext block being collected for the next keystream block.
+    register: Vec<u8>,
+    keystream: Vec<u8>,
+    /// Position inside the current keystream block.
+    position: usize,
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    /// Returns an error if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8]) -> Result<Self, &'static str> {
+        let block_size = cipher.block_size();
+        check_iv(iv, block_size)?;
+
+        Ok(Cfb {
+            cipher,
+            register: iv.to_vec(),
+            keystream: vec![0; block_size],
+            position: 0,
+        })
+    }
+
+    /// Returns the keystream byte for the current position, encrypting the
+    /// feedback register first if a new block starts here.
+    fn next_keystream_byte(&mut self) -> u8 {
+        if self.position == 0 {
+            self.keystream.copy_from_slice(&self.register);
+            self.cipher.encrypt_block(&mut self.keystream);
+        }
+
+        self.keystream[self.position]
+    }
+
+    /// Feeds a ciphertext byte back into the register and moves on.
+    fn feed_back(&mut self, ciphertext: u8) {
+        self.register[self.position] = ciphertext;
+        self.position = (self.position + 1) % self.register.len();
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cfb<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        for byte in data.iter_mut() {
+            *byte ^= self.next_keystream_byte();
+            self.feed_back(*byte);
+        }
+
+        Ok(())
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        for byte in data.iter_mut() {
+            let ciphertext = *byte;
+            *byte ^= self.next_keystream_byte();
+            self.feed_back(ciphertext);
+        }
+
+        Ok(())
+    }
+}
+
+/// Output feedback mode: the keystream is the IV encrypted over and over
+/// again, independently of the data. Encryption and decryption are the same
+/// operation and the last block may be partial.
+pub struct Ofb<C> {
+    cipher: C,
+    /// The current keystream block, or the IV before the first block.
+    output: Vec<u8>,
+    /// Position inside the current keystream block.
+    position: usize,
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    /// Returns an error if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8]) -> Result<Self, &'static str> {
+        check_iv(iv, cipher.block_size())?;
+
+        Ok(Ofb {
+            cipher,
+            output: iv.to_vec(),
+            position: 0,
+        })
+    }
+
+    /// XORs the keystream into `data`.
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            if self.position == 0 {
+                self.cipher.encrypt_block(&mut self.output);
+            }
+            *byte ^= self.output[self.position];
+            self.position = (self.position + 1) % self.output.len();
+        }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ofb<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+
+    fn decrypt_in_place(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aes::Aes, chal1::hex_to_bytes};
+
+    // NIST SP 800-38A, appendix F.
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a\
+                             ae2d8a571e03ac9c9eb76fac45af8e51\
+                             30c81c46a35ce411e5fbc1191a0a52ef\
+                             f69f2445df4f9b17ad2b417be66c3710";
+
+    fn aes() -> Aes {
+        Aes::new(&hex_to_bytes(KEY).unwrap()).unwrap()
+    }
+
+    /// Checks a mode against a known answer, both in one go and split into
+    /// uneven pieces, which must give the same result.
+    fn check_mode<M: BlockMode>(new_mode: impl Fn() -> M, ciphertext: &str, piece: usize) {
+        let plaintext = hex_to_bytes(PLAINTEXT).unwrap();
+        let ciphertext = hex_to_bytes(ciphertext).unwrap();
+
+        assert_eq!(new_mode().encrypt(&plaintext).unwrap(), ciphertext);
+        assert_eq!(new_mode().decrypt(&ciphertext).unwrap(), plaintext);
+
+        let mut mode = new_mode();
+        let mut pieces = Vec::new();
+        for chunk in plaintext.chunks(piece) {
+            pieces.extend(mode.encrypt(chunk).unwrap());
+        }
+        assert_eq!(pieces, ciphertext);
+
+        let mut mode = new_mode();
+        let mut pieces = Vec::new();
+        for chunk in ciphertext.chunks(piece) {
+            pieces.extend(mode.decrypt(chunk).unwrap());
+        }
+        assert_eq!(pieces, plaintext);
+    }
+
+    #[test]
+    fn test_ecb() {
+        check_mode(
+            || Ecb::new(aes()),
+            "3ad77bb40d7a3660a89ecaf32466ef97\
+             f5d3d58503b9699de785895a96fdbaaf\
+             43b1cd7f598ece23881b00e3ed030688\
+             7b0c785e27e8ad3f8223207104725dd4",
+            32,
+        );
+
+        //------------------------ partial block ------------------------//
+
+        let mut ecb = Ecb::new(aes());
+        assert_eq!(
+            ecb.encrypt(&[0; 15]),
+            Err("Data length should be a multiple of the block size")
+        );
+    }
+
+    #[test]
+    fn test_cbc() {
+        let iv = hex_to_bytes(IV).unwrap();
+        check_mode(
+            || Cbc::new(aes(), &iv).unwrap(),
+            "7649abac8119b246cee98e9b12e9197d\
+             5086cb9b507219ee95db113a917678b2\
+             73bed6b8e3c1743b7116e69e22229516\
+             3ff1caa1681fac09120eca307586e1a7",
+            16,
+        );
+
+        //-------------------------- invalid IV --------------------------//
+
+        assert_eq!(
+            Cbc::new(aes(), &iv[1..]).err(),
+            Some("IV length should be equal to the block size")
+        );
+    }
+
+    #[test]
+    fn test_cfb() {
+        let iv = hex_to_bytes(IV).unwrap();
+        check_mode(
+            || Cfb::new(aes(), &iv).unwrap(),
+            "3b3fd92eb72dad20333449f8e83cfb4a\
+             c8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4df\
+             c04b05357c5d1c0eeac4c66f9ff7f2e6",
+            7,
+        );
+    }
+
+    #[test]
+    fn test_ofb() {
+        let iv = hex_to_bytes(IV).unwrap();
+        check_mode(
+            || Ofb::new(aes(), &iv).unwrap(),
+            "3b3fd92eb72dad20333449f8e83cfb4a\
+             7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc\
+             304c6528f659c77866a510d9c1d6ae5e",
+            5,
+        );
+    }
+
+    #[test]
+    fn test_ctr() {
+        let counter = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+        check_mode(
+            || Ctr::new(aes(), &counter).unwrap(),
+            "874d6191b620e3261bef6864990db6ce\
+             9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab\
+             1e031dda2fbe03d1792170a0f3009cee",
+            3,
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! Block padding
+//!
+//! PKCS#7 is what the rest of the crate uses. The other schemes are here for
+//! interoperability and for padding oracle experiments; they all implement
+//! `Padding`, so they can be swapped in anywhere a padding is expected.
+
+use rand::RngCore;
+
+/// A padding scheme for block modes. `pad` extends the data to a multiple of
+/// the block size and `unpad` checks and strips the padding again. Callers can
+/// implement it for their own schemes and pass them anywhere a padding is
+/// expected.
+pub trait Padding {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str>;
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str>;
+}
+
+/// PKCS#7 padding, see `pkcs7_pad`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pkcs7;
+
+impl Padding for Pkcs7 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        pkcs7_pad(data, block_size)
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        pkcs7_unpad(data, block_size)
+    }
+}
+
+/// No padding at all. The data must already be a whole number of blocks, in
+/// both directions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoPadding;
+
+impl Padding for NoPadding {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        check_whole_blocks(data, block_size)?;
+        Ok(data.to_vec())
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        check_whole_blocks(data, block_size)?;
+        Ok(data.to_vec())
+    }
+}
+
+/// PKCS#7 padding: appends `n` bytes of value `n` so that the length becomes a
+/// multiple of the block size. A full block of padding is added when the data
+/// is already aligned, so the padding can always be removed unambiguously.
+/// Returns an error if the block size is not between 1 and 255.
+///
+/// # Examples
+/// ```
+/// use set1::padding::pkcs7_pad;
+/// let padded = pkcs7_pad(b"YELLOW SUBMARINE", 20).unwrap();
+///
+/// assert_eq!(padded, b"YELLOW SUBMARINE\x04\x04\x04\x04");
+/// ```
+pub fn pkcs7_pad(data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+    pad_with(data, block_size, |num_pad, padding| {
+        padding.fill(num_pad as u8);
+    })
+}
+
+/// Removes PKCS#7 padding. Returns an error if the data is not a whole number
+/// of blocks or the padding is malformed.
+///
+/// # Examples
+/// ```
+/// use set1::padding::pkcs7_unpad;
+/// let unpadded = pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16).unwrap();
+///
+/// assert_eq!(unpadded, b"ICE ICE BABY");
+/// assert!(pkcs7_unpad(b"ICE ICE BABY\x01\x02\x03\x04", 16).is_err());
+/// ```
+pub fn pkcs7_unpad(data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+    // the last byte tells how many bytes of padding there are, and every one
+    // of them must have that value.
+    let num_pad = length_byte(data, block_size, "Invalid PKCS#7 padding")?;
+    if data[data.len() - num_pad..]
+        .iter()
+        .any(|byte| *byte as usize != num_pad)
+    {
+        return Err("Invalid PKCS#7 padding");
+    }
+
+    Ok(data[..data.len() - num_pad].to_vec())
+}
+
+/// ANSI X.923 padding: zero bytes followed by a last byte holding the number
+/// of padding bytes. Like PKCS#7 it always adds at least one byte and needs a
+/// block size between 1 and 255.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AnsiX923;
+
+impl Padding for AnsiX923 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        pad_with(data, block_size, |num_pad, padding| {
+            padding[num_pad - 1] = num_pad as u8;
+        })
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        let num_pad = length_byte(data, block_size, "Invalid ANSI X.923 padding")?;
+        if data[data.len() - num_pad..data.len() - 1]
+            .iter()
+            .any(|byte| *byte != 0)
+        {
+            return Err("Invalid ANSI X.923 padding");
+        }
+
+        Ok(data[..data.len() - num_pad].to_vec())
+    }
+}
+
+/// ISO 10126 padding: random bytes followed by a last byte holding the number
+/// of padding bytes. Only the length byte can be checked when unpadding.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iso10126;
+
+impl Padding for Iso10126 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        pad_with(data, block_size, |num_pad, padding| {
+            rand::thread_rng().fill_bytes(&mut padding[..num_pad - 1]);
+            padding[num_pad - 1] = num_pad as u8;
+        })
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        let num_pad = length_byte(data, block_size, "Invalid ISO 10126 padding")?;
+
+        Ok(data[..data.len() - num_pad].to_vec())
+    }
+}
+
+/// ISO/IEC 7816-4 padding: a single 0x80 byte followed by zero bytes up to the
+/// end of the block. The padding length is not stored, so any block size
+/// works.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Iso7816;
+
+impl Padding for Iso7816 {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        if block_size == 0 {
+            return Err("Block size should not be zero");
+        }
+
+        let mut padded = data.to_vec();
+        padded.push(0x80);
+        padded.resize((data.len() / block_size + 1) * block_size, 0);
+
+        Ok(padded)
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        check_padded(data, block_size)?;
+
+        // the marker is the last non-zero byte, and it must be in the last
+        // block.
+        let last_block = &data[data.len() - block_size..];
+        match last_block.iter().rposition(|byte| *byte != 0) {
+            Some(i) if last_block[i] == 0x80 => Ok(data[..data.len() - block_size + i].to_vec()),
+            _ => Err("Invalid ISO/IEC 7816-4 padding"),
+        }
+    }
+}
+
+/// Zero padding: zero bytes up to the next multiple of the block size, and
+/// nothing when the data is already aligned. Unpadding strips every trailing
+/// zero, so it is only reversible for data that doesn't end in a zero byte.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+    fn pad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        if block_size == 0 {
+            return Err("Block size should not be zero");
+        }
+
+        let mut padded = data.to_vec();
+        padded.resize((data.len() + block_size - 1) / block_size * block_size, 0);
+
+        Ok(padded)
+    }
+
+    fn unpad(&self, data: &[u8], block_size: usize) -> Result<Vec<u8>, &'static str> {
+        check_whole_blocks(data, block_size)?;
+        let end = data
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+
+        Ok(data[..end].to_vec())
+    }
+}
+
+fn check_whole_blocks(data: &[u8], block_size: usize) -> Result<(), &'static str> {
+    if block_size == 0 || data.len() % block_size != 0 {
+        return Err("Data length should be a multiple of the block size");
+    }
+
+    Ok(())
+}
+
+/// Checks that padded data is at least one whole block.
+fn check_padded(data: &[u8], block_size: usize) -> Result<(), &'static str> {
+    if data.is_empty() || block_size == 0 || data.len() % block_size != 0 {
+        return Err("Padded data length should be a non-zero multiple of the block size");
+    }
+
+    Ok(())
+}
+
+/// Pads to the next multiple of the block size for the schemes that store the
+/// padding length in the last byte, so between 1 and `block_size` bytes are
+/// added. `fill` writes the padding bytes, given their number.
+fn pad_with<F>(data: &[u8], block_size: usize, fill: F) -> Result<Vec<u8>, &'static str>
+where
+    F: FnOnce(usize, &mut [u8]),
+{
+    if block_size == 0 || block_size > 255 {
+        return Err("Block size should be between 1 and 255 bytes");
+    }
+
+    let num_pad = block_size - data.len() % block_size;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + num_pad, 0);
+    fill(num_pad, &mut padded[data.len()..]);
+
+    Ok(padded)
+}
+
+/// Reads the padding length from the last byte, for the schemes that store
+/// it there, and checks that it fits in the last block.
+fn length_byte(data: &[u8], block_size: usize, error: &'static str) -> Result<usize, &'static str> {
+    check_padded(data, block_size)?;
+
+    let num_pad = *data.last().unwrap() as usize;
+    if num_pad == 0 || num_pad > block_size {
+        return Err(error);
+    }
+
+    Ok(num_pad)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pkcs7() {
+        //---------------------- partial last block ----------------------//
+
+        let padded = pkcs7_pad(b"ICE ICE BABY", 16).unwrap();
+        assert_eq!(padded, b"ICE ICE BABY\x04\x04\x04\x04");
+        assert_eq!(pkcs7_unpad(&padded, 16).unwrap(), b"ICE ICE BABY");
+
+        //-------------------- aligned data, full block --------------------//
+
+        let padded = pkcs7_pad(b"YELLOW SUBMARINE", 16).unwrap();
+        assert_eq!(padded.len(), 32);
+        assert_eq!(padded[16..], [16; 16]);
+        assert_eq!(pkcs7_unpad(&padded, 16).unwrap(), b"YELLOW SUBMARINE");
+
+        //------------------------- invalid padding -------------------------//
+
+        assert_eq!(
+            pkcs7_unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16),
+            Err("Invalid PKCS#7 padding")
+        );
+        assert_eq!(
+            pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04\x00", 16),
+            Err("Invalid PKCS#7 padding")
+        );
+        assert_eq!(
+            pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04", 16),
+            Err("Padded data length should be a non-zero multiple of the block size")
+        );
+        assert_eq!(
+            pkcs7_pad(b"ICE ICE BABY", 256),
+            Err("Block size should be between 1 and 255 bytes")
+        );
+    }
+
+    #[test]
+    fn test_no_padding() {
+        assert_eq!(
+            NoPadding.pad(b"YELLOW SUBMARINE", 16).unwrap(),
+            b"YELLOW SUBMARINE"
+        );
+        assert_eq!(
+            NoPadding.unpad(b"YELLOW SUBMARINE", 16).unwrap(),
+            b"YELLOW SUBMARINE"
+        );
+        assert_eq!(
+            NoPadding.pad(b"ICE ICE BABY", 16),
+            Err("Data length should be a multiple of the block size")
+        );
+    }
+
+    #[test]
+    fn test_other_schemes() {
+        //---------------------------- ANSI X.923 ---------------------------//
+
+        let padded = AnsiX923.pad(b"ICE ICE BABY", 16).unwrap();
+        assert_eq!(padded, b"ICE ICE BABY\x00\x00\x00\x04");
+        assert_eq!(AnsiX923.unpad(&padded, 16).unwrap(), b"ICE ICE BABY");
+        assert_eq!(
+            AnsiX923.unpad(b"ICE ICE BABY\x00\x01\x00\x04", 16),
+            Err("Invalid ANSI X.923 padding")
+        );
+
+        //----------------------------- ISO 10126 ----------------------------//
+
+        let padded = Iso10126.pad(b"ICE ICE BABY", 16).unwrap();
+        assert_eq!(padded[..12], *b"ICE ICE BABY");
+        assert_eq!(padded[15], 4);
+        assert_eq!(Iso10126.unpad(&padded, 16).unwrap(), b"ICE ICE BABY");
+        assert_eq!(
+            Iso10126.unpad(b"ICE ICE BABY\x00\x00\x00\x11", 16),
+            Err("Invalid ISO 10126 padding")
+        );
+
+        //--------------------------- ISO/IEC 7816-4 -------------------------//
+
+        let padded = Iso7816.pad(b"ICE ICE BABY", 16).unwrap();
+        assert_eq!(padded, b"ICE ICE BABY\x80\x00\x00\x00");
+        assert_eq!(Iso7816.unpad(&padded, 16).unwrap(), b"ICE ICE BABY");
+        let padded = Iso7816.pad(b"YELLOW SUBMARINE", 16).unwrap();
+        assert_eq!(padded[16..], [&[0x80][..], &[0; 15]].concat());
+        assert_eq!(Iso7816.unpad(&padded, 16).unwrap(), b"YELLOW SUBMARINE");
+        assert_eq!(
+            Iso7816.unpad(b"ICE ICE BABY\x80\x00\x01\x00", 16),
+            Err("Invalid ISO/IEC 7816-4 padding")
+        );
+        assert_eq!(
+            Iso7816.unpad(&[0; 16], 16),
+            Err("Invalid ISO/IEC 7816-4 padding")
+        );
+
+        //---------------------------- zero padding --------------------------//
+
+        let padded = ZeroPadding.pad(b"ICE ICE BABY", 16).unwrap();
+        assert_eq!(padded, b"ICE ICE BABY\x00\x00\x00\x00");
+        assert_eq!(ZeroPadding.unpad(&padded, 16).unwrap(), b"ICE ICE BABY");
+        assert_eq!(
+            ZeroPadding.pad(b"YELLOW SUBMARINE", 16).unwrap(),
+            b"YELLOW SUBMARINE"
+        );
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let schemes: [&dyn Padding; 5] = [&Pkcs7, &AnsiX923, &Iso10126, &Iso7816, &ZeroPadding];
+        for scheme in schemes {
+            for block_size in [1, 8, 16, 255] {
+                for len in 0..40 {
+                    // data ending in a non-zero byte, so zero padding can be
+                    // removed too.
+                    let data: Vec<u8> = (1..=len as u8).collect();
+
+                    let padded = scheme.pad(&data, block_size).unwrap();
+                    assert!(padded.len() % block_size == 0);
+                    assert_eq!(scheme.unpad(&padded, block_size).unwrap(), data);
+                }
+            }
+
+            assert!(scheme.pad(b"ICE ICE BABY", 0).is_err());
+            assert!(scheme.unpad(b"ICE ICE BABY\x04\x04\x04", 16).is_err());
+        }
+    }
+}
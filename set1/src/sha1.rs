@@ -0,0 +1,177 @@
+//! SHA-1 (FIPS 180-4)
+
+/// Size of a SHA-1 digest in bytes.
+pub const DIGEST_SIZE: usize = 20;
+
+/// Size of the blocks SHA-1 compresses, in bytes.
+pub const SHA1_BLOCK_SIZE: usize = 64;
+
+/// The initial hash value.
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// Incremental SHA-1. Data can be fed in pieces of any size with `update`.
+///
+/// # Examples
+/// ```
+/// use set1::sha1::{sha1, Sha1};
+///
+/// let mut hasher = Sha1::new();
+/// hasher.update(b"ab");
+/// hasher.update(b"c");
+///
+/// assert_eq!(hasher.finalize(), sha1(b"abc"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sha1 {
+    state: [u32; 5],
+    /// Data not yet compressed, always shorter than a block.
+    buffer: Vec<u8>,
+    /// Total length of the data in bytes.
+    length: u64,
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1::new()
+    }
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Sha1 {
+            state: INITIAL_STATE,
+            buffer: Vec::with_capacity(SHA1_BLOCK_SIZE),
+            length: 0,
+        }
+    }
+
+    /// Feeds more data to the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        self.buffer.extend_from_slice(data);
+
+        let whole = self.buffer.len() - self.buffer.len() % SHA1_BLOCK_SIZE;
+        for block in self.buffer[..whole].chunks_exact(SHA1_BLOCK_SIZE) {
+            compress(&mut self.state, block);
+        }
+        self.buffer.drain(..whole);
+    }
+
+    /// Pads the data with a one bit, zeroes and the bit length, and returns
+    /// the digest.
+    pub fn finalize(mut self) -> [u8; DIGEST_SIZE] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        let mut padding = vec![0x80];
+        let used = (self.buffer.len() + 1) % SHA1_BLOCK_SIZE;
+        padding.resize(1 + (SHA1_BLOCK_SIZE + 56 - used) % SHA1_BLOCK_SIZE, 0);
+        padding.extend_from_slice(&bit_length.to_be_bytes());
+
+        // `update` would count the padding in the length.
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        let mut digest = [0u8; DIGEST_SIZE];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// Returns the SHA-1 digest of `data`.
+///
+/// # Examples
+/// ```
+/// use set1::sha1::sha1;
+///
+/// assert_eq!(sha1(b"abc")[..4], [0xa9, 0x99, 0x3e, 0x36]);
+/// ```
+pub fn sha1(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Compresses one 64 byte block into the state.
+fn compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for t in 16..80 {
+        w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (t, word) in w.iter().enumerate() {
+        let (f, k) = match t {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    #[test]
+    fn test_sha1_vectors() {
+        let vectors: [(&[u8], &str); 3] = [
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+        ];
+        for (message, digest) in vectors {
+            assert_eq!(sha1(message).to_vec(), hex_to_bytes(digest).unwrap());
+        }
+
+        // a million `a`s, fed in uneven pieces.
+        let mut hasher = Sha1::new();
+        let data = vec![b'a'; 1_000_000];
+        for piece in data.chunks(999) {
+            hasher.update(piece);
+        }
+        assert_eq!(
+            hasher.finalize().to_vec(),
+            hex_to_bytes("34aa973cd4c4daa4f61eeb2bdbad27316534016f").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sha1_padding_boundaries() {
+        // lengths around the point where the length no longer fits in the
+        // last block.
+        for len in 50..70 {
+            let data = vec![0x5a; len];
+            let mut hasher = Sha1::new();
+            hasher.update(&data[..len / 2]);
+            hasher.update(&data[len / 2..]);
+            assert_eq!(hasher.finalize(), sha1(&data));
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! AES-SIV (RFC 5297)
+//!
+//! Synthetic IV mode: a CMAC based PRF (S2V) over the associated data and the
+//! plaintext gives a 16 byte tag, which also serves as the IV for CTR
+//! encryption. The same inputs always give the same ciphertext, so it can be
+//! used without a nonce for key wrapping; a nonce, when there is one, is just
+//! the last piece of associated data.
+
+use crate::{
+    aes::{Aes, Backend, BLOCK_SIZE},
+    chal2::xor_in_place,
+    cmac::{dbl, Cmac},
+    modes::{BlockMode, Ctr},
+};
+use std::fmt;
+
+/// S2V can take at most this many strings, the plaintext included.
+const MAX_COMPONENTS: usize = 127;
+
+/// Why encryption or decryption failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SivError {
+    /// The key is not 32, 48 or 64 bytes long.
+    InvalidKey,
+    /// The ciphertext is shorter than the tag, or there are too many pieces
+    /// of associated data.
+    InvalidLength,
+    /// The tag doesn't match the decrypted plaintext and associated data.
+    AuthenticationFailed,
+    /// The chosen AES backend is not supported by this CPU.
+    BackendUnavailable,
+}
+
+impl fmt::Display for SivError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            SivError::InvalidKey => "SIV key should be 32, 48 or 64 bytes",
+            SivError::InvalidLength => "Invalid length for the SIV input",
+            SivError::AuthenticationFailed => "SIV authentication failed",
+            SivError::BackendUnavailable => "AES instructions are not available on this CPU",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for SivError {}
+
+/// AES-SIV with a double length key: the first half keys S2V and the second
+/// half CTR.
+///
+/// # Examples
+/// ```
+/// use set1::siv::{AesSiv, SivError};
+///
+/// let siv = AesSiv::new(b"YELLOW SUBMARINEPURPLE SUBMARINE").unwrap();
+/// let mut ciphertext = siv.encrypt(&[b"header"], b"ICE ICE BABY").unwrap();
+/// assert_eq!(ciphertext.len(), 16 + 12);
+///
+/// assert_eq!(siv.decrypt(&[b"header"], &ciphertext).unwrap(), b"ICE ICE BABY");
+///
+/// ciphertext[20] ^= 1;
+/// assert_eq!(
+///     siv.decrypt(&[b"header"], &ciphertext),
+///     Err(SivError::AuthenticationFailed)
+/// );
+/// ```
+pub struct AesSiv {
+    mac: Cmac,
+    ctr_key: Aes,
+}
+
+impl AesSiv {
+    /// Uses `Backend::detect()`, so the key never goes through the table
+    /// backend. Returns an error if the key is not 32, 48 or 64 bytes long.
+    pub fn new(key: &[u8]) -> Result<Self, SivError> {
+        AesSiv::with_backend(key, Backend::detect())
+    }
+
+    /// Returns an error if the key is not 32, 48 or 64 bytes long or the
+    /// backend is not supported by this CPU.
+    pub fn with_backend(key: &[u8], backend: Backend) -> Result<Self, SivError> {
+        if !backend.is_available() {
+            return Err(SivError::BackendUnavailable);
+        }
+        if ![32, 48, 64].contains(&key.len()) {
+            return Err(SivError::InvalidKey);
+        }
+
+        let (mac_key, ctr_key) = key.split_at(key.len() / 2);
+        Ok(AesSiv {
+            mac: Cmac::with_backend(mac_key, backend).map_err(|_| SivError::InvalidKey)?,
+            ctr_key: Aes::with_backend(ctr_key, backend).map_err(|_| SivError::InvalidKey)?,
+        })
+    }
+
+    /// Encrypts `plaintext` and returns the tag followed by the ciphertext.
+    /// Every piece of `associated_data` is authenticated separately, in
+    /// order.
+    pub fn encrypt(
+        &self,
+        associated_data: &[&[u8]],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, SivError> {
+        let tag = self.s2v(associated_data, plaintext)?;
+
+        let mut output = tag.to_vec();
+        output.extend_from_slice(&self.ctr(&tag, plaintext));
+        Ok(output)
+    }
+
+    /// Decrypts the output of `encrypt` and checks the tag.
+    pub fn decrypt(
+        &self,
+        associated_data: &[&[u8]],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, SivError> {
+        if ciphertext.len() < BLOCK_SIZE {
+            return Err(SivError::InvalidLength);
+        }
+
+        let (tag, ciphertext) = ciphertext.split_at(BLOCK_SIZE);
+        let plaintext = self.ctr(tag.try_into().unwrap(), ciphertext);
+
+        // compare every byte, so the time doesn't tell how much matched.
+        let expected = self.s2v(associated_data, &plaintext)?;
+        let difference = expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if difference != 0 {
+            return Err(SivError::AuthenticationFailed);
+        }
+
+        Ok(plaintext)
+    }
+
+    /// The S2V PRF of RFC 5297 section 2.4, over the associated data and the
+    /// plaintext.
+    fn s2v(
+        &self,
+        associated_data: &[&[u8]],
+        plaintext: &[u8],
+    ) -> Result<[u8; BLOCK_SIZE], SivError> {
+        if associated_data.len() + 1 > MAX_COMPONENTS {
+            return Err(SivError::InvalidLength);
+        }
+
+        let mut d = self.mac.mac(&[0; BLOCK_SIZE]);
+        for data in associated_data {
+            d = dbl(&d);
+            xor_in_place(&mut d, &self.mac.mac(data));
+        }
+
+        // the plaintext is mixed in differently depending on whether it has a
+        // full block: XORed into its end, or padded and XORed with dbl(D).
+        let last = if plaintext.len() >= BLOCK_SIZE {
+            let mut last = plaintext.to_vec();
+            let start = last.len() - BLOCK_SIZE;
+            xor_in_place(&mut last[start..], &d);
+            last
+        } else {
+            let mut last = dbl(&d);
+            let mut padded = [0u8; BLOCK_SIZE];
+            padded[..plaintext.len()].copy_from_slice(plaintext);
+            padded[plaintext.len()] = 0x80;
+            xor_in_place(&mut last, &padded);
+            last.to_vec()
+        };
+
+        Ok(self.mac.mac(&last))
+    }
+
+    /// CTR with the tag as the initial counter block, after clearing the top
+    /// bit of the last two 32-bit words so implementations with 32 or 64-bit
+    /// counters agree.
+    fn ctr(&self, tag: &[u8; BLOCK_SIZE], data: &[u8]) -> Vec<u8> {
+        let mut counter = *tag;
+        counter[8] &= 0x7f;
+        counter[12] &= 0x7f;
+
+        let mut output = data.to_vec();
+        Ctr::new(&self.ctr_key, &counter)
+            .and_then(|mut ctr| ctr.encrypt_in_place(&mut output))
+            .expect("the counter block is one block long");
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    #[test]
+    fn test_rfc_5297_vectors() {
+        //------------------ A.1, deterministic encryption ------------------//
+
+        let siv = AesSiv::new(
+            &hex_to_bytes("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff")
+                .unwrap(),
+        )
+        .unwrap();
+        let ad = hex_to_bytes("101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+        let plaintext = hex_to_bytes("112233445566778899aabbccddee").unwrap();
+        let expected =
+            hex_to_bytes("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c").unwrap();
+
+        assert_eq!(siv.encrypt(&[&ad], &plaintext).unwrap(), expected);
+        assert_eq!(siv.decrypt(&[&ad], &expected).unwrap(), plaintext);
+
+        //------------------- A.2, nonce-based encryption -------------------//
+
+        let siv = AesSiv::new(
+            &hex_to_bytes("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f")
+                .unwrap(),
+        )
+        .unwrap();
+        let ad1 = hex_to_bytes(
+            "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100",
+        )
+        .unwrap();
+        let ad2 = hex_to_bytes("102030405060708090a0").unwrap();
+        let nonce = hex_to_bytes("09f911029d74e35bd84156c5635688c0").unwrap();
+        let plaintext = b"this is some plaintext to encrypt using SIV-AES";
+        let expected = hex_to_bytes(
+            "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+             dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d",
+        )
+        .unwrap();
+
+        let associated_data: [&[u8]; 3] = [&ad1, &ad2, &nonce];
+        assert_eq!(siv.encrypt(&associated_data, plaintext).unwrap(), expected);
+        assert_eq!(siv.decrypt(&associated_data, &expected).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_siv_errors() {
+        let siv = AesSiv::new(&[1; 64]).unwrap();
+        let ciphertext = siv.encrypt(&[b"one", b"two"], b"ICE ICE BABY").unwrap();
+
+        // the associated data is bound to the tag, pieces and order included.
+        assert_eq!(
+            siv.decrypt(&[b"two", b"one"], &ciphertext),
+            Err(SivError::AuthenticationFailed)
+        );
+        assert_eq!(
+            siv.decrypt(&[b"onetwo"], &ciphertext),
+            Err(SivError::AuthenticationFailed)
+        );
+
+        assert_eq!(siv.decrypt(&[], &[0; 15]), Err(SivError::InvalidLength));
+        assert_eq!(AesSiv::new(&[0; 16]).err(), Some(SivError::InvalidKey));
+    }
+
+    #[test]
+    fn test_backends() {
+        let siv = AesSiv::new(&[1; 32]).unwrap();
+        assert_ne!(siv.ctr_key.backend(), Backend::Table);
+
+        let table = AesSiv::with_backend(&[1; 32], Backend::Table).unwrap();
+        assert_eq!(
+            siv.encrypt(&[b"header"], b"ICE ICE BABY").unwrap(),
+            table.encrypt(&[b"header"], b"ICE ICE BABY").unwrap()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Integral (Square) attack on 4-round AES-128
+//!
+//! A Λ-set is 256 plaintexts that take every value in one byte and agree on
+//! the other 15. After three rounds every byte of the state is balanced: its
+//! values XOR to zero over the set. The fourth round only adds SubBytes,
+//! ShiftRows and the last round key (plus MixColumns, if the cipher keeps it),
+//! so each byte of the last round key can be guessed on its own by undoing
+//! that round on one ciphertext byte and checking that the result is balanced.
+
+use crate::aes::{inv_mix_columns, invert_key_schedule, mix_columns, ReducedAes};
+use crate::aes::{BLOCK_SIZE, INV_SBOX};
+
+/// Number of rounds the attack works against.
+pub const ROUNDS: usize = 4;
+
+/// The attack stops if a key byte is still ambiguous after this many Λ-sets.
+const MAX_LAMBDA_SETS: usize = 16;
+
+/// Builds the Λ-set number `index`: the first byte runs through every value
+/// and the other bytes are a constant that is different for every set.
+pub fn lambda_set(index: usize) -> Vec<[u8; BLOCK_SIZE]> {
+    (0..=255u8)
+        .map(|active| {
+            let mut plaintext: [u8; BLOCK_SIZE] =
+                core::array::from_fn(|i| (index * 31 + i * 7) as u8);
+            plaintext[0] = active;
+            plaintext
+        })
+        .collect()
+}
+
+/// Recovers the last round key of 4-round AES from a chosen-plaintext
+/// encryption oracle. `final_mix_columns` tells whether the last round of the
+/// target keeps MixColumns. Λ-sets are encrypted until every key byte has a
+/// single candidate left, which usually takes two or three sets.
+pub fn recover_last_round_key<F>(
+    mut encrypt: F,
+    final_mix_columns: bool,
+) -> Result<[u8; BLOCK_SIZE], &'static str>
+where
+    F: FnMut(&[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE],
+{
+    // candidates[j][g] is true while g is still possible for key byte j.
+    let mut candidates = [[true; 256]; BLOCK_SIZE];
+
+    for index in 0..MAX_LAMBDA_SETS {
+        let ciphertexts: Vec<[u8; BLOCK_SIZE]> = lambda_set(index)
+            .iter()
+            .map(|plaintext| {
+                let mut ciphertext = encrypt(plaintext);
+                // MixColumns is linear, so undoing it leaves SubBytes and
+                // ShiftRows followed by the key InvMixColumns(k4).
+                if final_mix_columns {
+                    inv_mix_columns(&mut ciphertext);
+                }
+                ciphertext
+            })
+            .collect();
+
+        for (position, candidates) in candidates.iter_mut().enumerate() {
+            for guess in 0..=255u8 {
+                if candidates[guess as usize] && !is_balanced(&ciphertexts, position, guess) {
+                    candidates[guess as usize] = false;
+                }
+            }
+        }
+
+        if candidates
+            .iter()
+            .all(|candidates| candidates.iter().filter(|c| **c).count() == 1)
+        {
+            let mut round_key: [u8; BLOCK_SIZE] = core::array::from_fn(|position| {
+                candidates[position].iter().position(|c| *c).unwrap() as u8
+            });
+            if final_mix_columns {
+                mix_columns(&mut round_key);
+            }
+            return Ok(round_key);
+        }
+
+        if candidates
+            .iter()
+            .any(|candidates| !candidates.contains(&true))
+        {
+            return Err("No key byte survived, the oracle is not 4-round AES");
+        }
+    }
+
+    Err("Key bytes are still ambiguous after the maximum number of Λ-sets")
+}
+
+/// Runs the attack and inverts the key schedule from the recovered round key
+/// to get the AES-128 master key. The key is checked by encrypting one more
+/// plaintext with it and comparing to the oracle.
+///
+/// # Examples
+/// ```
+/// use set1::aes::ReducedAes;
+/// use set1::square::{square_attack, ROUNDS};
+///
+/// let target = ReducedAes::new(b"YELLOW SUBMARINE", ROUNDS, false).unwrap();
+/// let key = square_attack(
+///     |plaintext| {
+///         let mut block = *plaintext;
+///         target.encrypt_block(&mut block);
+///         block
+///     },
+///     false,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(key, b"YELLOW SUBMARINE");
+/// ```
+pub fn square_attack<F>(mut encrypt: F, final_mix_columns: bool) -> Result<Vec<u8>, &'static str>
+where
+    F: FnMut(&[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE],
+{
+    let round_key = recover_last_round_key(&mut encrypt, final_mix_columns)?;
+    let key = invert_key_schedule(&round_key, ROUNDS)?;
+
+    // the schedule is a bijection, so a wrong round key still inverts to some
+    // key; only the check against the oracle tells them apart.
+    let cipher = ReducedAes::new(&key, ROUNDS, final_mix_columns)?;
+    let mut block = [0x5a; BLOCK_SIZE];
+    let expected = encrypt(&block);
+    cipher.encrypt_block(&mut block);
+    if block != expected {
+        return Err("Recovered key does not match the oracle");
+    }
+
+    Ok(key)
+}
+
+/// Undoes the last round on byte `position` of every ciphertext with `guess`
+/// as the key byte, and checks that the results XOR to zero.
+fn is_balanced(ciphertexts: &[[u8; BLOCK_SIZE]], position: usize, guess: u8) -> bool {
+    ciphertexts.iter().fold(0, |sum, ciphertext| {
+        sum ^ INV_SBOX[(ciphertext[position] ^ guess) as usize]
+    }) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aes::Aes;
+
+    #[test]
+    fn test_square_attack() {
+        for final_mix_columns in [false, true] {
+            for seed in 0..4u8 {
+                let key: Vec<u8> = (0..16u8)
+                    .map(|i| i.wrapping_mul(73) ^ (seed * 41))
+                    .collect();
+                let target = ReducedAes::new(&key, ROUNDS, final_mix_columns).unwrap();
+
+                let mut queries = 0;
+                let recovered = square_attack(
+                    |plaintext| {
+                        queries += 1;
+                        let mut block = *plaintext;
+                        target.encrypt_block(&mut block);
+                        block
+                    },
+                    final_mix_columns,
+                )
+                .unwrap();
+
+                assert_eq!(recovered, key);
+                assert!(queries <= MAX_LAMBDA_SETS * 256 + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lambda_set_is_balanced_after_three_rounds() {
+        let three_rounds = ReducedAes::new(b"YELLOW SUBMARINE", 3, true).unwrap();
+
+        let sum = lambda_set(0)
+            .iter()
+            .fold([0u8; BLOCK_SIZE], |mut sum, plaintext| {
+                let mut block = *plaintext;
+                three_rounds.encrypt_block(&mut block);
+                sum.iter_mut().zip(block).for_each(|(s, b)| *s ^= b);
+                sum
+            });
+        assert_eq!(sum, [0; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn test_attack_fails_on_full_aes() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let result = square_attack(
+            |plaintext| {
+                let mut block = *plaintext;
+                aes.encrypt_block(&mut block);
+                block
+            },
+            false,
+        );
+
+        assert!(result.is_err());
+    }
+}
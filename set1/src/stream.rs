@@ -0,0 +1,474 @@
+//! Streaming encryption and decryption
+//!
+//! `io::Write` and `io::Read` adapters that run any `BlockMode` over a stream
+//! of bytes, so large inputs never have to be loaded at once. Data goes
+//! through in chunks of at most `CHUNK_SIZE` bytes; only the bytes of an
+//! unfinished block (and, when decrypting padded data, the last block) are
+//! held back. Padding is added or removed once, at the end of the stream.
+
+use crate::{modes::BlockMode, padding::Padding};
+use std::io::{self, Read, Write};
+
+/// The most data processed in one go. Together with one held back block it
+/// bounds the memory an adapter uses, whatever the size of the stream.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The part shared by every adapter: runs the mode over whole blocks as they
+/// come in and deals with the tail and the padding at the end.
+struct Engine<M> {
+    mode: M,
+    padding: Option<Box<dyn Padding>>,
+    encrypt: bool,
+    /// Input that has not gone through the mode yet.
+    pending: Vec<u8>,
+}
+
+impl<M: BlockMode> Engine<M> {
+    fn new(mode: M, padding: Option<Box<dyn Padding>>, encrypt: bool) -> Self {
+        Engine {
+            mode,
+            padding,
+            encrypt,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Takes up to `CHUNK_SIZE` bytes of `input`, appends what can be
+    /// processed already to `output` and returns how many bytes were taken.
+    fn update(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize, &'static str> {
+        let input = &input[..input.len().min(CHUNK_SIZE)];
+        self.pending.extend_from_slice(input);
+
+        // the last block of padded ciphertext has to wait for the end of the
+        // stream, since only then do we know that it holds the padding.
+        let block_size = self.mode.block_size();
+        let mut ready = self.pending.len() - self.pending.len() % block_size;
+        if !self.encrypt && self.padding.is_some() && ready == self.pending.len() {
+            ready = ready.saturating_sub(block_size);
+        }
+
+        let start = output.len();
+        output.extend(self.pending.drain(..ready));
+        self.apply(&mut output[start..])?;
+
+        Ok(input.len())
+    }
+
+    /// Processes the held back bytes at the end of the stream, with the
+    /// padding if there is one.
+    fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), &'static str> {
+        let mut tail = std::mem::take(&mut self.pending);
+        let block_size = self.mode.block_size();
+
+        match (self.padding.take(), self.encrypt) {
+            (Some(padding), true) => {
+                tail = padding.pad(&tail, block_size)?;
+                self.apply(&mut tail)?;
+            }
+            (Some(padding), false) => {
+                self.apply(&mut tail)?;
+                tail = padding.unpad(&tail, block_size)?;
+            }
+            // without padding, the mode decides whether a partial block is
+            // fine (CFB, OFB, CTR) or an error (ECB, CBC).
+            (None, _) => self.apply(&mut tail)?,
+        }
+
+        output.extend_from_slice(&tail);
+        Ok(())
+    }
+
+    fn apply(&mut self, data: &mut [u8]) -> Result<(), &'static str> {
+        if self.encrypt {
+            self.mode.encrypt_in_place(data)
+        } else {
+            self.mode.decrypt_in_place(data)
+        }
+    }
+}
+
+fn invalid_data(error: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// A writer that runs the mode over everything written to it and passes the
+/// result on to `inner`. `finish` must be called at the end of the stream to
+/// handle the last block and the padding; dropping the writer without it
+/// discards the tail, so a stream cut short never looks complete.
+///
+/// Data the mode has processed can't be taken back, so once a write fails
+/// the stream is broken: every later write and `finish` return an error.
+///
+/// # Examples
+/// ```
+/// use set1::{aes::Aes, modes::{BlockMode, Cbc}, padding::Pkcs7};
+/// use set1::stream::CryptoWriter;
+/// use std::io::Write;
+///
+/// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+/// let mode = Cbc::new(&aes, &[0; 16]).unwrap();
+///
+/// let mut writer = CryptoWriter::encrypt(Vec::new(), mode, Some(Box::new(Pkcs7)));
+/// writer.write_all(b"ICE ICE ").unwrap();
+/// writer.write_all(b"BABY").unwrap();
+/// let ciphertext = writer.finish().unwrap();
+///
+/// let expected = Cbc::new(&aes, &[0; 16]).unwrap()
+///     .encrypt(b"ICE ICE BABY\x04\x04\x04\x04")
+///     .unwrap();
+/// assert_eq!(ciphertext, expected);
+/// ```
+pub struct CryptoWriter<W: Write, M: BlockMode> {
+    inner: W,
+    engine: Engine<M>,
+    output: Vec<u8>,
+    /// Set when a write failed after the mode had taken the data.
+    poisoned: bool,
+}
+
+impl<W: Write, M: BlockMode> CryptoWriter<W, M> {
+    /// Encrypts what is written and writes the ciphertext to `inner`. The
+    /// padding is added at the end; without it the plaintext has to fit the
+    /// mode (whole blocks for ECB and CBC).
+    pub fn encrypt(inner: W, mode: M, padding: Option<Box<dyn Padding>>) -> Self {
+        CryptoWriter {
+            inner,
+            engine: Engine::new(mode, padding, true),
+            output: Vec::new(),
+            poisoned: false,
+        }
+    }
+
+    /// Decrypts what is written and writes the plaintext to `inner`. The
+    /// padding is checked and removed at the end.
+    pub fn decrypt(inner: W, mode: M, padding: Option<Box<dyn Padding>>) -> Self {
+        CryptoWriter {
+            inner,
+            engine: Engine::new(mode, padding, false),
+            output: Vec::new(),
+            poisoned: false,
+        }
+    }
+
+    /// Processes the end of the stream, flushes and returns the inner writer.
+    /// Bad padding or a partial block the mode can't take is reported as
+    /// `io::ErrorKind::InvalidData`, a stream broken by an earlier failed
+    /// write as `io::ErrorKind::BrokenPipe`.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.check_poisoned()?;
+        self.output.clear();
+        self.engine.finish(&mut self.output).map_err(invalid_data)?;
+        self.inner.write_all(&self.output)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    fn check_poisoned(&self) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The stream is broken by an earlier failed write",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write, M: BlockMode> Write for CryptoWriter<W, M> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_poisoned()?;
+
+        // the mode has moved on once it took the data, so a failure past this
+        // point leaves a gap in the stream.
+        self.output.clear();
+        let written = self
+            .engine
+            .update(buf, &mut self.output)
+            .map_err(invalid_data)
+            .and_then(|taken| self.inner.write_all(&self.output).map(|_| taken));
+        self.poisoned = written.is_err();
+
+        written
+    }
+
+    /// Flushes the inner writer. Held back bytes stay until `finish`, since
+    /// they can't be processed before the stream is complete.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that runs the mode over the bytes read from `inner`. The end of
+/// the inner stream is the end of the message, where the padding is handled.
+///
+/// # Examples
+/// ```
+/// use set1::{aes::Aes, modes::Ecb, padding::Pkcs7};
+/// use set1::stream::CryptoReader;
+/// use std::io::Read;
+///
+/// let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+/// let ciphertext = set1::chal7::aes_ecb_encrypt(b"ICE ICE BABY", b"YELLOW SUBMARINE", &Pkcs7)
+///     .unwrap();
+///
+/// let mut reader = CryptoReader::decrypt(&ciphertext[..], Ecb::new(&aes), Some(Box::new(Pkcs7)));
+/// let mut plaintext = Vec::new();
+/// reader.read_to_end(&mut plaintext).unwrap();
+///
+/// assert_eq!(plaintext, b"ICE ICE BABY");
+/// ```
+pub struct CryptoReader<R: Read, M: BlockMode> {
+    inner: R,
+    engine: Engine<M>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// How much of `output` has been handed out already.
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read, M: BlockMode> CryptoReader<R, M> {
+    /// Reads plaintext from `inner` and returns the ciphertext.
+    pub fn encrypt(inner: R, mode: M, padding: Option<Box<dyn Padding>>) -> Self {
+        CryptoReader::new(inner, Engine::new(mode, padding, true))
+    }
+
+    /// Reads ciphertext from `inner` and returns the plaintext.
+    pub fn decrypt(inner: R, mode: M, padding: Option<Box<dyn Padding>>) -> Self {
+        CryptoReader::new(inner, Engine::new(mode, padding, false))
+    }
+
+    fn new(inner: R, engine: Engine<M>) -> Self {
+        CryptoReader {
+            inner,
+            engine,
+            input: vec![0; CHUNK_SIZE],
+            output: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, M: BlockMode> Read for CryptoReader<R, M> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // refill until there is something to hand out or the stream is over;
+        // a short chunk may all be held back.
+        while self.position == self.output.len() && !self.done {
+            self.output.clear();
+            self.position = 0;
+
+            let count = match self.inner.read(&mut self.input) {
+                Ok(count) => count,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            if count == 0 {
+                self.done = true;
+                self.engine.finish(&mut self.output).map_err(invalid_data)?;
+            } else {
+                self.engine
+                    .update(&self.input[..count], &mut self.output)
+                    .map_err(invalid_data)?;
+            }
+        }
+
+        let available = &self.output[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aes::Aes;
+    use crate::modes::{Cbc, Cfb, Ctr, Ecb, Ofb};
+    use crate::padding::{Iso7816, Pkcs7};
+
+    /// Writes `data` in pieces of `piece` bytes.
+    fn write_in_pieces<W: Write>(writer: &mut W, data: &[u8], piece: usize) {
+        for chunk in data.chunks(piece) {
+            writer.write_all(chunk).unwrap();
+        }
+    }
+
+    /// Reads everything in reads of at most `piece` bytes.
+    fn read_in_pieces<R: Read>(reader: &mut R, piece: usize) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut buf = vec![0; piece];
+        loop {
+            let count = reader.read(&mut buf)?;
+            if count == 0 {
+                return Ok(data);
+            }
+            data.extend_from_slice(&buf[..count]);
+        }
+    }
+
+    #[test]
+    fn test_matches_one_shot() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = [7u8; 16];
+
+        type NewMode<'a> = Box<dyn Fn() -> Box<dyn BlockMode + 'a> + 'a>;
+        let modes: Vec<(NewMode, bool)> = vec![
+            (Box::new(|| Box::new(Ecb::new(&aes))), true),
+            (Box::new(|| Box::new(Cbc::new(&aes, &iv).unwrap())), true),
+            (Box::new(|| Box::new(Cfb::new(&aes, &iv).unwrap())), false),
+            (Box::new(|| Box::new(Ofb::new(&aes, &iv).unwrap())), false),
+            (Box::new(|| Box::new(Ctr::new(&aes, &iv).unwrap())), false),
+        ];
+
+        for (new_mode, needs_padding) in &modes {
+            let padding = || -> Option<Box<dyn Padding>> {
+                needs_padding.then(|| Box::new(Pkcs7) as Box<dyn Padding>)
+            };
+
+            for len in [0, 1, 15, 16, 17, 100, 1000] {
+                let plaintext: Vec<u8> = (0..len).map(|i| (i * 13) as u8).collect();
+                let padded = match padding() {
+                    Some(padding) => padding.pad(&plaintext, 16).unwrap(),
+                    None => plaintext.clone(),
+                };
+                let expected = new_mode().encrypt(&padded).unwrap();
+
+                for piece in [1, 5, 16, 33] {
+                    let mut mode = new_mode();
+                    let mut writer = CryptoWriter::encrypt(Vec::new(), &mut *mode, padding());
+                    write_in_pieces(&mut writer, &plaintext, piece);
+                    assert_eq!(writer.finish().unwrap(), expected);
+
+                    let mut mode = new_mode();
+                    let mut reader = CryptoReader::encrypt(&plaintext[..], &mut *mode, padding());
+                    assert_eq!(read_in_pieces(&mut reader, piece).unwrap(), expected);
+
+                    let mut mode = new_mode();
+                    let mut writer = CryptoWriter::decrypt(Vec::new(), &mut *mode, padding());
+                    write_in_pieces(&mut writer, &expected, piece);
+                    assert_eq!(writer.finish().unwrap(), plaintext);
+
+                    let mut mode = new_mode();
+                    let mut reader = CryptoReader::decrypt(&expected[..], &mut *mode, padding());
+                    assert_eq!(read_in_pieces(&mut reader, piece).unwrap(), plaintext);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+
+        //----------------- partial block without padding ------------------//
+
+        let mut writer = CryptoWriter::encrypt(Vec::new(), Ecb::new(&aes), None);
+        writer.write_all(b"ICE ICE BABY").unwrap();
+        let error = writer.finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        //-------------------------- bad padding ---------------------------//
+
+        let ciphertext = Ecb::new(&aes).encrypt(&[0; 32]).unwrap();
+        let mut reader =
+            CryptoReader::decrypt(&ciphertext[..], Ecb::new(&aes), Some(Box::new(Iso7816)));
+        let error = read_in_pieces(&mut reader, 64).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Invalid ISO/IEC 7816-4 padding");
+    }
+
+    #[test]
+    fn test_unfinished_writer() {
+        // without `finish` the held back tail is never padded and written, so
+        // only the whole blocks come out.
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let plaintext = b"YELLOW SUBMARINE, BABY";
+        let expected = Cbc::new(&aes, &[0; 16])
+            .unwrap()
+            .encrypt(&Pkcs7.pad(plaintext, 16).unwrap())
+            .unwrap();
+
+        let mut ciphertext = Vec::new();
+        let mode = Cbc::new(&aes, &[0; 16]).unwrap();
+        let mut writer = CryptoWriter::encrypt(&mut ciphertext, mode, Some(Box::new(Pkcs7)));
+        writer.write_all(plaintext).unwrap();
+        drop(writer);
+        assert_eq!(ciphertext, expected[..16]);
+    }
+
+    #[test]
+    fn test_poisoned_writer() {
+        // the inner writer fails once, after the mode has taken the data.
+        #[derive(Debug)]
+        struct FailOnce(bool);
+        impl Write for FailOnce {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if std::mem::replace(&mut self.0, false) {
+                    return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+                }
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mode = Ctr::new(&aes, &[0; 16]).unwrap();
+        let mut writer = CryptoWriter::encrypt(FailOnce(true), mode, None);
+
+        assert_eq!(writer.write(&[0; 32]).unwrap_err().to_string(), "disk full");
+        let error = writer.write(&[0; 32]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        let error = writer.finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_bounded_memory() {
+        // a stream much larger than the chunk size goes through a reader and a
+        // writer, while the buffers stay the same size.
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let len = 16 * CHUNK_SIZE as u64 + 5;
+        let source = io::repeat(0x42).take(len);
+
+        let mut encryptor = CryptoReader::encrypt(
+            source,
+            Cbc::new(&aes, &[0; 16]).unwrap(),
+            Some(Box::new(Pkcs7)),
+        );
+        let mut decryptor = CryptoWriter::decrypt(
+            io::sink(),
+            Cbc::new(&aes, &[0; 16]).unwrap(),
+            Some(Box::new(Pkcs7)),
+        );
+
+        let mut buf = vec![0; 3 * CHUNK_SIZE];
+        let mut total = 0;
+        loop {
+            let count = encryptor.read(&mut buf).unwrap();
+            if count == 0 {
+                break;
+            }
+            decryptor.write_all(&buf[..count]).unwrap();
+            total += count as u64;
+
+            assert!(encryptor.output.capacity() <= 2 * CHUNK_SIZE);
+            assert!(decryptor.output.capacity() <= 2 * CHUNK_SIZE);
+            assert!(decryptor.engine.pending.capacity() <= 2 * CHUNK_SIZE);
+        }
+        decryptor.finish().unwrap();
+
+        assert_eq!(total, (len / 16 + 1) * 16);
+    }
+}
@@ -0,0 +1,366 @@
+//! XTS-AES (IEEE 1619)
+//!
+//! The tweakable mode used for disk encryption. A disk is cut into data units
+//! (sectors), and every sector is encrypted on its own with a tweak derived
+//! from its number, so equal sectors at different places encrypt differently
+//! and any sector can be read or written without touching the others. Inside
+//! a sector the tweak is multiplied by α for every block, and a last partial
+//! block is handled with ciphertext stealing.
+
+use crate::{
+    aes::{Aes, Backend, BLOCK_SIZE},
+    chal2::xor_in_place,
+};
+
+/// XTS-AES-128 or XTS-AES-256. The key is two different AES keys of the same
+/// size: the first one encrypts the data and the second one the tweak.
+///
+/// # Examples
+/// ```
+/// use set1::xts::{sector_tweak, Xts};
+///
+/// let xts = Xts::new(b"YELLOW SUBMARINEsixteen byte key").unwrap();
+/// assert!(Xts::new(b"YELLOW SUBMARINEYELLOW SUBMARINE").is_err());
+/// let mut sector = *b"the first block and a bit more";
+///
+/// xts.encrypt_sector(&sector_tweak(7), &mut sector).unwrap();
+/// xts.decrypt_sector(&sector_tweak(7), &mut sector).unwrap();
+/// assert_eq!(&sector, b"the first block and a bit more");
+/// ```
+pub struct Xts {
+    data_cipher: Aes,
+    tweak_cipher: Aes,
+}
+
+/// Returns the tweak of a sector: its number as a 128-bit little-endian
+/// integer, as IEEE 1619 specifies.
+pub fn sector_tweak(sector: u128) -> [u8; BLOCK_SIZE] {
+    sector.to_le_bytes()
+}
+
+impl Xts {
+    /// Uses `Backend::detect()`, so the keys never go through the table
+    /// backend. Returns an error if the key is not 32 or 64 bytes long, or
+    /// if its two halves are equal, which IEEE 1619 forbids.
+    pub fn new(key: &[u8]) -> Result<Self, &'static str> {
+        Xts::with_backend(key, Backend::detect())
+    }
+
+    /// Returns an error if the key is not valid for `new` or the backend is
+    /// not supported by this CPU.
+    pub fn with_backend(key: &[u8], backend: Backend) -> Result<Self, &'static str> {
+        if key.len() != 32 && key.len() != 64 {
+            return Err("XTS key should be 32 or 64 bytes, two AES-128 or AES-256 keys");
+        }
+
+        // compared without an early exit, like a tag.
+        let (data_key, tweak_key) = key.split_at(key.len() / 2);
+        let difference = data_key
+            .iter()
+            .zip(tweak_key)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if difference == 0 {
+            return Err("XTS data and tweak keys should be different");
+        }
+
+        Ok(Xts {
+            data_cipher: Aes::with_backend(data_key, backend)?,
+            tweak_cipher: Aes::with_backend(tweak_key, backend)?,
+        })
+    }
+
+    /// Encrypts one sector in place. The sector must be at least one block
+    /// long, but doesn't have to be a whole number of blocks.
+    pub fn encrypt_sector(
+        &self,
+        tweak: &[u8; BLOCK_SIZE],
+        data: &mut [u8],
+    ) -> Result<(), &'static str> {
+        let tweaks = self.tweaks(tweak, data.len())?;
+        let full_blocks = data.len() / BLOCK_SIZE;
+        let partial = data.len() % BLOCK_SIZE;
+
+        for (block, tweak) in data.chunks_exact_mut(BLOCK_SIZE).zip(&tweaks) {
+            self.encrypt_block(block, tweak);
+        }
+
+        // ciphertext stealing: the partial block takes the start of the last
+        // full ciphertext block, and the rest of that block pads the partial
+        // plaintext, which is encrypted into the last full position.
+        if partial > 0 {
+            let last_full = (full_blocks - 1) * BLOCK_SIZE;
+            let (head, partial_block) = data.split_at_mut(full_blocks * BLOCK_SIZE);
+            let stolen = &mut head[last_full..];
+
+            let mut block = [0u8; BLOCK_SIZE];
+            block[..partial].copy_from_slice(partial_block);
+            block[partial..].copy_from_slice(&stolen[partial..]);
+            partial_block.copy_from_slice(&stolen[..partial]);
+
+            self.encrypt_block(&mut block, &tweaks[full_blocks]);
+            stolen.copy_from_slice(&block);
+        }
+
+        Ok(())
+    }
+
+    /// Decrypts one sector in place.
+    pub fn decrypt_sector(
+        &self,
+        tweak: &[u8; BLOCK_SIZE],
+        data: &mut [u8],
+    ) -> Result<(), &'static str> {
+        let tweaks = self.tweaks(tweak, data.len())?;
+        let full_blocks = data.len() / BLOCK_SIZE;
+        let partial = data.len() % BLOCK_SIZE;
+
+        // with stealing, the last full block was encrypted with the tweak of
+        // the partial block, and has to be undone first.
+        let regular = if partial > 0 {
+            full_blocks - 1
+        } else {
+            full_blocks
+        };
+        for (block, tweak) in data[..regular * BLOCK_SIZE]
+            .chunks_exact_mut(BLOCK_SIZE)
+            .zip(&tweaks)
+        {
+            self.decrypt_block(block, tweak);
+        }
+
+        if partial > 0 {
+            let (head, partial_block) = data.split_at_mut(full_blocks * BLOCK_SIZE);
+            let stolen = &mut head[regular * BLOCK_SIZE..];
+
+            let mut block: [u8; BLOCK_SIZE] = (&*stolen).try_into().unwrap();
+            self.decrypt_block(&mut block, &tweaks[full_blocks]);
+
+            stolen[..partial].copy_from_slice(partial_block);
+            stolen[partial..].copy_from_slice(&block[partial..]);
+            partial_block.copy_from_slice(&block[..partial]);
+
+            self.decrypt_block(stolen, &tweaks[regular]);
+        }
+
+        Ok(())
+    }
+
+    /// Encrypts consecutive sectors of `sector_size` bytes in place, the
+    /// first one being sector number `first_sector`.
+    pub fn encrypt_sectors(
+        &self,
+        data: &mut [u8],
+        sector_size: usize,
+        first_sector: u128,
+    ) -> Result<(), &'static str> {
+        check_sectors(data, sector_size)?;
+        for (sector, number) in data.chunks_exact_mut(sector_size).zip(first_sector..) {
+            self.encrypt_sector(&sector_tweak(number), sector)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decrypts consecutive sectors of `sector_size` bytes in place, the
+    /// first one being sector number `first_sector`.
+    pub fn decrypt_sectors(
+        &self,
+        data: &mut [u8],
+        sector_size: usize,
+        first_sector: u128,
+    ) -> Result<(), &'static str> {
+        check_sectors(data, sector_size)?;
+        for (sector, number) in data.chunks_exact_mut(sector_size).zip(first_sector..) {
+            self.decrypt_sector(&sector_tweak(number), sector)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encrypts the tweak and returns its multiples by α, one for every block
+    /// of the sector, the partial one included.
+    fn tweaks(
+        &self,
+        tweak: &[u8; BLOCK_SIZE],
+        len: usize,
+    ) -> Result<Vec<[u8; BLOCK_SIZE]>, &'static str> {
+        if len < BLOCK_SIZE {
+            return Err("XTS data unit should be at least one block");
+        }
+
+        let mut tweak = *tweak;
+        self.tweak_cipher.encrypt_block(&mut tweak);
+
+        let mut tweaks = Vec::with_capacity((len + BLOCK_SIZE - 1) / BLOCK_SIZE);
+        for _ in 0..(len + BLOCK_SIZE - 1) / BLOCK_SIZE {
+            tweaks.push(tweak);
+            mul_alpha(&mut tweak);
+        }
+
+        Ok(tweaks)
+    }
+
+    fn encrypt_block(&self, block: &mut [u8], tweak: &[u8; BLOCK_SIZE]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().unwrap();
+        xor_in_place(block, tweak);
+        self.data_cipher.encrypt_block(block);
+        xor_in_place(block, tweak);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8], tweak: &[u8; BLOCK_SIZE]) {
+        let block: &mut [u8; BLOCK_SIZE] = block.try_into().unwrap();
+        xor_in_place(block, tweak);
+        self.data_cipher.decrypt_block(block);
+        xor_in_place(block, tweak);
+    }
+}
+
+fn check_sectors(data: &[u8], sector_size: usize) -> Result<(), &'static str> {
+    if sector_size == 0 || data.len() % sector_size != 0 {
+        return Err("Data length should be a multiple of the sector size");
+    }
+
+    Ok(())
+}
+
+/// Multiplies the tweak by α in GF(2^128). XTS reads the block as a
+/// little-endian number, unlike GCM, so this is a left shift with the
+/// reduction x^128 = x^7 + x^2 + x + 1 folded back into the first byte.
+fn mul_alpha(tweak: &mut [u8; BLOCK_SIZE]) {
+    let value = u128::from_le_bytes(*tweak);
+    let carry = (value >> 127) as u8;
+    *tweak = (value << 1).to_le_bytes();
+    tweak[0] ^= 0x87 & carry.wrapping_neg();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::hex_to_bytes;
+
+    /// Checks one IEEE 1619 vector in both directions.
+    fn check_vector(key: &str, tweak: [u8; BLOCK_SIZE], plaintext: &[u8], ciphertext: &str) {
+        let xts = Xts::new(&hex_to_bytes(key).unwrap()).unwrap();
+        let ciphertext = hex_to_bytes(ciphertext).unwrap();
+
+        let mut data = plaintext.to_vec();
+        xts.encrypt_sector(&tweak, &mut data).unwrap();
+        assert_eq!(data, ciphertext);
+
+        xts.decrypt_sector(&tweak, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn test_ieee_1619_vectors() {
+        //------------------------------ vector 2 -----------------------------//
+
+        // vector 1 uses the same key for the data and the tweak, which the
+        // 2018 revision of the standard rules out.
+        check_vector(
+            "1111111111111111111111111111111122222222222222222222222222222222",
+            sector_tweak(0x3333333333),
+            &[0x44; 32],
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+        );
+
+        //------------------ vectors 15-18, ciphertext stealing ----------------//
+
+        let key = "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0";
+        let tweak = sector_tweak(0x123456789a);
+        let plaintext: Vec<u8> = (0..20).collect();
+        check_vector(
+            key,
+            tweak,
+            &plaintext[..17],
+            "6c1625db4671522d3d7599601de7ca09ed",
+        );
+        check_vector(
+            key,
+            tweak,
+            &plaintext[..18],
+            "d069444b7a7e0cab09e24447d24deb1fedbf",
+        );
+        check_vector(
+            key,
+            tweak,
+            &plaintext[..19],
+            "e5df1351c0544ba1350b3363cd8ef4beedbf9d",
+        );
+        check_vector(
+            key,
+            tweak,
+            &plaintext[..20],
+            "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac",
+        );
+    }
+
+    #[test]
+    fn test_ieee_1619_sectors() {
+        // vectors 4 (XTS-AES-128) and 10 (XTS-AES-256): a 512 byte sector of
+        // the bytes 0 to 255 twice. Only the start and the end are checked.
+        let plaintext: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        let vectors = [
+            (
+                "2718281828459045235360287471352631415926535897932384626433832795",
+                0,
+                "27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89c",
+                "0a282df920147beabe421ee5319d0568",
+            ),
+            (
+                "27182818284590452353602874713526624977572470936999595749669676273141592653589793238462643383279502884197169399375105820974944592",
+                0xff,
+                "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b",
+                "c4f36ffda9fcea70b9c6e693e148c151",
+            ),
+        ];
+        for (key, sector, start, end) in vectors {
+            let xts = Xts::new(&hex_to_bytes(key).unwrap()).unwrap();
+            let table = Xts::with_backend(&hex_to_bytes(key).unwrap(), Backend::Table).unwrap();
+            assert_ne!(xts.data_cipher.backend(), Backend::Table);
+
+            let mut data = plaintext.clone();
+            table.encrypt_sectors(&mut data, 512, sector).unwrap();
+            assert_eq!(data[..32], hex_to_bytes(start).unwrap());
+
+            let mut data = plaintext.clone();
+            xts.encrypt_sectors(&mut data, 512, sector).unwrap();
+            assert_eq!(data[..32], hex_to_bytes(start).unwrap());
+            assert_eq!(data[496..], hex_to_bytes(end).unwrap());
+
+            xts.decrypt_sectors(&mut data, 512, sector).unwrap();
+            assert_eq!(data, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_sectors_are_independent() {
+        let key: Vec<u8> = (0..64).collect();
+        let xts = Xts::new(&key).unwrap();
+        let mut disk = vec![0u8; 4 * 512];
+        xts.encrypt_sectors(&mut disk, 512, 10).unwrap();
+
+        // equal sectors encrypt differently, and each one can be decrypted
+        // on its own with its number.
+        assert_ne!(disk[..512], disk[512..1024]);
+        let mut third = disk[1024..1536].to_vec();
+        xts.decrypt_sector(&sector_tweak(12), &mut third).unwrap();
+        assert_eq!(third, [0; 512]);
+
+        //------------------------------ errors -----------------------------//
+
+        assert!(Xts::new(&[0; 48]).is_err());
+        assert_eq!(
+            Xts::new(&[0x5a; 64]).err(),
+            Some("XTS data and tweak keys should be different")
+        );
+        assert_eq!(
+            xts.encrypt_sector(&sector_tweak(0), &mut [0; 15]),
+            Err("XTS data unit should be at least one block")
+        );
+        assert_eq!(
+            xts.encrypt_sectors(&mut disk, 500, 0),
+            Err("Data length should be a multiple of the sector size")
+        );
+    }
+}
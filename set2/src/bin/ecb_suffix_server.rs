@@ -0,0 +1,24 @@
+//! Serves the challenge 12 ECB suffix oracle over the TCP line protocol.
+//!
+//! Usage: ecb_suffix_server [ADDR], listening on 127.0.0.1:9012 by default.
+
+use set2::{chal4::EcbSuffixOracle, oracle::Oracle, transport::serve_lines};
+use std::{env, net::TcpListener, sync::Mutex};
+
+fn main() -> std::io::Result<()> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:9012".to_string());
+    let oracle = Mutex::new(EcbSuffixOracle::challenge(&mut rand::thread_rng()));
+
+    let listener = TcpListener::bind(&addr)?;
+    println!("ECB suffix oracle listening on {}", listener.local_addr()?);
+
+    serve_lines(listener, move |input| {
+        oracle
+            .lock()
+            .unwrap()
+            .query(input)
+            .map_err(|error| error.to_string())
+    })
+}
@@ -1,28 +1,10 @@
-/// # PKCS#7 padding: The plaintext is padded with a number of bytes such that
-/// the length of the plaintext is a multiple of the block size. The value of
-/// each byte of the padding is equal to the number of bytes of padding.
-///
-/// Arguments:
-/// * `plaintext` - The plaintext to be padded.
-/// * `final_length` - The length of the padded plaintext.
-pub fn apply_padding(plaintext: String, final_length: usize) -> String {
-    if final_length < plaintext.len() {
-        panic!("Final length is less than plaintext length");
-    }
-    let num_pad = final_length - plaintext.len();
-
-    // serialize the plaintext first.
-    let mut plaintext_serialized = plaintext.as_bytes().to_vec();
-
-    // add `num_pad` pads to the serialized plaintext.
-    plaintext_serialized.append(&mut vec![num_pad as u8; num_pad]);
+//! Implement PKCS#7 padding
+//!
+//! The padding lives in `set1::padding`, next to the block modes that use it.
+//! It works on bytes, pads to a multiple of the block size and has a strict
+//! unpad counterpart.
 
-    // deserialize padded plaintext.
-    let padded_plaintext = String::from_utf8(plaintext_serialized)
-        .expect("Deserialization of padded plaintext failed");
-
-    padded_plaintext
-}
+pub use set1::padding::{pkcs7_pad, pkcs7_unpad};
 
 #[cfg(test)]
 mod test {
@@ -30,8 +12,14 @@ mod test {
 
     #[test]
     fn test_apply_padding() {
-        let plaintext = String::from("YELLOW SUBMARINE");
-        let padded_plaintext = apply_padding(plaintext, 20);
-        assert_eq!(padded_plaintext, "YELLOW SUBMARINE\x04\x04\x04\x04");
+        let padded_plaintext = pkcs7_pad(b"YELLOW SUBMARINE", 20).unwrap();
+        assert_eq!(padded_plaintext, b"YELLOW SUBMARINE\x04\x04\x04\x04");
+        assert_eq!(
+            pkcs7_unpad(&padded_plaintext, 20).unwrap(),
+            b"YELLOW SUBMARINE"
+        );
+
+        // a pad longer than 255 bytes can't be written in one byte.
+        assert!(pkcs7_pad(b"YELLOW SUBMARINE", 300).is_err());
     }
 }
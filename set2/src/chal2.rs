@@ -0,0 +1,114 @@
+//! Implement CBC mode
+
+use set1::{
+    aes::{Aes, BLOCK_SIZE},
+    chal6::base64_to_bytes,
+    modes::{BlockMode, Cbc},
+    padding::{pkcs7_pad, pkcs7_unpad},
+};
+use std::fs;
+
+/// Encrypts `plaintext` with AES in CBC mode. The plaintext is PKCS#7 padded
+/// first, so it can have any length. The key can be 16, 24 or 32 bytes long and
+/// the IV must be exactly one block (16 bytes).
+///
+/// # Examples
+/// ```
+/// use set2::chal2::{aes_cbc_decrypt, aes_cbc_encrypt};
+///
+/// let key = b"YELLOW SUBMARINE";
+/// let iv = [0u8; 16];
+/// let ciphertext = aes_cbc_encrypt(b"ICE ICE BABY", key, &iv).unwrap();
+///
+/// assert_eq!(ciphertext.len(), 16);
+/// assert_eq!(aes_cbc_decrypt(&ciphertext, key, &iv).unwrap(), b"ICE ICE BABY");
+/// ```
+pub fn aes_cbc_encrypt(plaintext: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut cbc = Cbc::new(Aes::new(key)?, iv)?;
+    let padded = pkcs7_pad(plaintext, BLOCK_SIZE)?;
+
+    cbc.encrypt(&padded)
+}
+
+/// Decrypts `ciphertext` with AES in CBC mode and removes the PKCS#7 padding.
+/// Returns an error if the key or IV has the wrong size, the ciphertext is not
+/// a whole number of blocks, or the padding is invalid.
+pub fn aes_cbc_decrypt(ciphertext: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut cbc = Cbc::new(Aes::new(key)?, iv)?;
+    let padded = cbc.decrypt(ciphertext)?;
+
+    pkcs7_unpad(&padded, BLOCK_SIZE)
+}
+
+/// This function decrypts the ciphertext in set2/data/chal10/10.txt using AES
+/// in CBC mode. The key is "YELLOW SUBMARINE" and the IV is all zeroes. The
+/// decrypted text is saved to set2/data/chal10/10_decryption.txt.
+pub fn cbc_mode() {
+    // read the file.
+    let cipher = fs::read_to_string("set2/data/chal10/10.txt").unwrap();
+
+    // replace the newline characters with empty strings.
+    let cipher = cipher.replace("\n", "");
+    let cipher_bytes = base64_to_bytes(&cipher).unwrap();
+
+    // decrypt the ciphertext.
+    let key = "YELLOW SUBMARINE";
+    let iv = [0u8; BLOCK_SIZE];
+    let decrypt_bytes = aes_cbc_decrypt(&cipher_bytes, key.as_bytes(), &iv).unwrap();
+
+    println!(
+        "Decryption: {}",
+        String::from_utf8(decrypt_bytes.clone()).unwrap()
+    );
+
+    // save the decrypted text to a file.
+    fs::write("set2/data/chal10/10_decryption.txt", decrypt_bytes).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cbc_mode() {
+        let cipher = include_str!("../data/chal10/10.txt").replace('\n', "");
+        let cipher_bytes = base64_to_bytes(&cipher).unwrap();
+        let iv = [0u8; BLOCK_SIZE];
+
+        let plaintext = aes_cbc_decrypt(&cipher_bytes, b"YELLOW SUBMARINE", &iv).unwrap();
+        let plaintext = String::from_utf8(plaintext).unwrap();
+
+        assert!(plaintext.starts_with("I'm back and I'm ringin' the bell \n"));
+        assert!(plaintext.ends_with("Play that funky music \n"));
+
+        // encrypting the plaintext again gives back the file.
+        let encrypted = aes_cbc_encrypt(plaintext.as_bytes(), b"YELLOW SUBMARINE", &iv).unwrap();
+        assert_eq!(encrypted, cipher_bytes);
+    }
+
+    #[test]
+    fn test_cbc_iv_handling() {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = b"The same plaintext under two different IVs";
+
+        // a different IV gives a different ciphertext for the same plaintext.
+        let first = aes_cbc_encrypt(plaintext, key, &[0u8; 16]).unwrap();
+        let second = aes_cbc_encrypt(plaintext, key, &[1u8; 16]).unwrap();
+        assert_ne!(first, second);
+
+        // decrypting with the wrong IV only garbles the first block.
+        let garbled = Cbc::new(Aes::new(key).unwrap(), &[1u8; 16])
+            .unwrap()
+            .decrypt(&first)
+            .unwrap();
+        assert_ne!(garbled[..16], plaintext[..16]);
+        assert_eq!(garbled[16..plaintext.len()], plaintext[16..]);
+
+        //--------------------------- invalid IV ---------------------------//
+
+        assert_eq!(
+            aes_cbc_encrypt(plaintext, key, &[0u8; 8]),
+            Err("IV length should be equal to the block size")
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! An ECB/CBC detection oracle
+
+use rand::Rng;
+use set1::{aes::BLOCK_SIZE, chal7::aes_ecb_encrypt, chal8::duplicate_checker, padding::Pkcs7};
+
+use crate::chal2::aes_cbc_encrypt;
+
+/// The block cipher mode the oracle picked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+}
+
+/// Returns `len` random bytes.
+pub fn random_bytes<R: Rng + ?Sized>(rng: &mut R, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Returns a random 16 byte AES key.
+pub fn random_aes_key<R: Rng + ?Sized>(rng: &mut R) -> [u8; BLOCK_SIZE] {
+    let mut key = [0u8; BLOCK_SIZE];
+    rng.fill_bytes(&mut key);
+    key
+}
+
+/// This function encrypts `input` under a random key, after adding 5 to 10
+/// random bytes before and after it. Half of the time it uses ECB, and the
+/// other half CBC with a random IV. The mode is returned with the ciphertext
+/// so the guesses can be scored.
+pub fn encryption_oracle<R: Rng + ?Sized>(rng: &mut R, input: &[u8]) -> (Vec<u8>, Mode) {
+    let key = random_aes_key(rng);
+
+    let prefix_len = rng.gen_range(5..=10);
+    let suffix_len = rng.gen_range(5..=10);
+    let mut plaintext = random_bytes(rng, prefix_len);
+    plaintext.extend_from_slice(input);
+    plaintext.extend(random_bytes(rng, suffix_len));
+
+    if rng.gen_bool(0.5) {
+        let ciphertext = aes_ecb_encrypt(&plaintext, &key, &Pkcs7).unwrap();
+        (ciphertext, Mode::Ecb)
+    } else {
+        let iv = random_bytes(rng, BLOCK_SIZE);
+        let ciphertext = aes_cbc_encrypt(&plaintext, &key, &iv).unwrap();
+        (ciphertext, Mode::Cbc)
+    }
+}
+
+/// This function guesses the mode of an encryption oracle. It sends three
+/// blocks of the same byte: whatever the random prefix length, at least two
+/// of them fill whole blocks, which ECB encrypts to equal ciphertext blocks
+/// and CBC doesn't.
+///
+/// # Examples
+/// ```
+/// use set2::chal3::{detect_mode, encryption_oracle};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut mode = None;
+/// let guess = detect_mode(|input| {
+///     let (ciphertext, actual) = encryption_oracle(&mut rng, input);
+///     mode = Some(actual);
+///     ciphertext
+/// });
+///
+/// assert_eq!(Some(guess), mode);
+/// ```
+pub fn detect_mode<F>(oracle: F) -> Mode
+where
+    F: FnOnce(&[u8]) -> Vec<u8>,
+{
+    let ciphertext = oracle(&[b'A'; 3 * BLOCK_SIZE]);
+
+    if duplicate_checker(&ciphertext, BLOCK_SIZE) {
+        Mode::Ecb
+    } else {
+        Mode::Cbc
+    }
+}
+
+/// Plays `rounds` rounds of the detection game and returns the fraction of
+/// correct guesses.
+pub fn detection_accuracy<R: Rng + ?Sized>(rng: &mut R, rounds: usize) -> f64 {
+    let mut correct = 0;
+    for _ in 0..rounds {
+        let mut actual = None;
+        let guess = detect_mode(|input| {
+            let (ciphertext, mode) = encryption_oracle(rng, input);
+            actual = Some(mode);
+            ciphertext
+        });
+
+        if Some(guess) == actual {
+            correct += 1;
+        }
+    }
+
+    correct as f64 / rounds as f64
+}
+
+/// This function plays the detection game against the oracle and prints how
+/// often the mode was guessed right.
+pub fn ecb_cbc_detection_oracle() {
+    let rounds = 1000;
+    let accuracy = detection_accuracy(&mut rand::thread_rng(), rounds);
+
+    println!(
+        "Detected the mode in {:.1}% of {} rounds",
+        accuracy * 100.0,
+        rounds
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_encryption_oracle() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut seen = (false, false);
+
+        for _ in 0..100 {
+            let (ciphertext, mode) = encryption_oracle(&mut rng, b"YELLOW SUBMARINE");
+
+            // 16 bytes of input and 10 to 20 random bytes, padded.
+            assert!(ciphertext.len() == 32 || ciphertext.len() == 48);
+            match mode {
+                Mode::Ecb => seen.0 = true,
+                Mode::Cbc => seen.1 = true,
+            }
+        }
+
+        assert_eq!(seen, (true, true));
+    }
+
+    #[test]
+    fn test_detection_accuracy() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let rounds = 5000;
+        let accuracy = detection_accuracy(&mut rng, rounds);
+
+        println!("accuracy over {} rounds: {:.4}", rounds, accuracy);
+        assert_eq!(accuracy, 1.0);
+    }
+}
@@ -0,0 +1,421 @@
+//! Byte-at-a-time ECB decryption (Simple)
+//!
+//! The oracle appends an unknown suffix to our input and encrypts the result
+//! with ECB under an unknown key. By making the input one byte short of a
+//! block boundary, the block holds 15 known bytes and the first unknown one,
+//! which we find by trying all 256 values for the last byte of the same block.
+//! Shifting the boundary one byte at a time recovers the whole suffix.
+
+use rand::Rng;
+use set1::{
+    chal6::base64_to_bytes, chal7::aes_ecb_encrypt, chal8::duplicate_checker, padding::Pkcs7,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+};
+
+use crate::{
+    chal3::random_aes_key,
+    oracle::{Oracle, OracleError},
+};
+
+/// The secret the challenge oracle appends to every input.
+const SECRET_SUFFIX: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkg\
+                             aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq\
+                             dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg\
+                             YnkK";
+
+/// Largest block size the attack looks for.
+pub(crate) const MAX_BLOCK_SIZE: usize = 64;
+
+/// The challenge 12 oracle: AES-128-ECB of `input || suffix` under a key
+/// chosen at construction.
+pub struct EcbSuffixOracle {
+    key: [u8; 16],
+    suffix: Vec<u8>,
+}
+
+impl EcbSuffixOracle {
+    /// Creates the oracle with a random key and the given secret suffix.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, suffix: &[u8]) -> Self {
+        EcbSuffixOracle {
+            key: random_aes_key(rng),
+            suffix: suffix.to_vec(),
+        }
+    }
+
+    /// Creates the oracle with a random key and the suffix of the challenge.
+    pub fn challenge<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        EcbSuffixOracle::new(rng, &base64_to_bytes(SECRET_SUFFIX).unwrap())
+    }
+}
+
+impl Oracle for EcbSuffixOracle {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        let mut plaintext = input.to_vec();
+        plaintext.extend_from_slice(&self.suffix);
+
+        aes_ecb_encrypt(&plaintext, &self.key, &Pkcs7)
+            .map_err(|error| OracleError::Rejected(error.to_string()))
+    }
+}
+
+/// Why the attack stopped.
+#[derive(Debug)]
+pub enum AttackError {
+    /// The oracle failed to answer.
+    Oracle(OracleError),
+    /// The ciphertext length never jumped, so there is no block cipher or the
+    /// block size is above the supported maximum.
+    BlockSizeNotFound,
+    /// Repeated input blocks didn't give repeated ciphertext blocks.
+    NotEcb,
+    /// No byte value matched at this position of the suffix, so the oracle
+    /// doesn't behave like a deterministic ECB oracle.
+    ByteNotFound(usize),
+    /// The attack would have gone over its query budget.
+    QueryLimitExceeded(usize),
+    /// An answer was too short or its length contradicted earlier ones.
+    UnexpectedAnswer,
+}
+
+impl fmt::Display for AttackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttackError::Oracle(error) => write!(f, "{}", error),
+            AttackError::BlockSizeNotFound => write!(f, "Could not find the block size"),
+            AttackError::NotEcb => write!(f, "The oracle does not use ECB"),
+            AttackError::ByteNotFound(position) => {
+                write!(f, "No byte matched at position {} of the suffix", position)
+            }
+            AttackError::QueryLimitExceeded(limit) => {
+                write!(f, "The attack needs more than {} queries", limit)
+            }
+            AttackError::UnexpectedAnswer => write!(f, "The oracle answered something unexpected"),
+        }
+    }
+}
+
+impl std::error::Error for AttackError {}
+
+impl From<OracleError> for AttackError {
+    fn from(error: OracleError) -> Self {
+        AttackError::Oracle(error)
+    }
+}
+
+/// Where the attack is, passed to the progress callback after every byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub recovered: usize,
+    pub total: usize,
+    pub queries: usize,
+    pub max_queries: usize,
+}
+
+/// The outcome of a successful attack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttackReport {
+    pub block_size: usize,
+    pub suffix: Vec<u8>,
+    /// Queries actually made.
+    pub queries: usize,
+    /// The most queries the attack could have needed for this suffix, when
+    /// there is a useful bound.
+    pub max_queries: Option<usize>,
+}
+
+/// Counts the queries and stops at the budget.
+pub(crate) struct Counted<O> {
+    pub(crate) oracle: O,
+    pub(crate) queries: usize,
+    pub(crate) limit: usize,
+}
+
+impl<O: Oracle<Output = Vec<u8>>> Counted<O> {
+    pub(crate) fn new(oracle: O) -> Self {
+        Counted {
+            oracle,
+            queries: 0,
+            limit: usize::MAX,
+        }
+    }
+
+    pub(crate) fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, AttackError> {
+        if self.queries >= self.limit {
+            return Err(AttackError::QueryLimitExceeded(self.limit));
+        }
+        self.queries += 1;
+
+        Ok(self.oracle.query(input)?)
+    }
+}
+
+/// How our input lines up with the blocks of the oracle's plaintext.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Layout {
+    pub(crate) block_size: usize,
+    /// Bytes to send first so that what follows starts a new block.
+    pub(crate) filler: usize,
+    /// Index of the first block that starts with our own bytes.
+    pub(crate) first_block: usize,
+    /// Length of the unknown suffix.
+    pub(crate) suffix_len: usize,
+}
+
+impl Layout {
+    /// Queries for one suffix byte: one per possible value, plus the
+    /// reference ciphertexts, which are made once per block offset.
+    pub(crate) fn max_queries(&self) -> usize {
+        self.block_size + 256 * self.suffix_len
+    }
+}
+
+/// This function runs the byte-at-a-time attack against an ECB oracle that
+/// appends a secret to its input. It finds the block size and the suffix
+/// length from the jumps in ciphertext length, checks for ECB with repeated
+/// blocks, and then recovers the suffix one byte at a time, calling
+/// `progress` after each one. The number of queries is reported and is at
+/// most `AttackReport::max_queries`, known once the suffix length is.
+///
+/// # Examples
+/// ```
+/// use set2::chal4::{byte_at_a_time, EcbSuffixOracle};
+///
+/// let mut oracle = EcbSuffixOracle::new(&mut rand::thread_rng(), b"attack at dawn");
+/// let report = byte_at_a_time(&mut oracle, |_| {}).unwrap();
+///
+/// assert_eq!(report.block_size, 16);
+/// assert_eq!(report.suffix, b"attack at dawn");
+/// assert!(report.queries <= report.max_queries.unwrap());
+/// ```
+pub fn byte_at_a_time<O, F>(oracle: O, progress: F) -> Result<AttackReport, AttackError>
+where
+    O: Oracle<Output = Vec<u8>>,
+    F: FnMut(&Progress),
+{
+    let mut oracle = Counted::new(oracle);
+
+    let (block_size, suffix_len) = find_block_size(&mut oracle, 0)?;
+    let confirm = oracle.query(&vec![b'A'; 2 * block_size])?;
+    if !duplicate_checker(&confirm, block_size) {
+        return Err(AttackError::NotEcb);
+    }
+
+    let layout = Layout {
+        block_size,
+        filler: 0,
+        first_block: 0,
+        suffix_len,
+    };
+    recover_suffix(&mut oracle, layout, progress)
+}
+
+/// Finds the block size from the first jump in ciphertext length as the
+/// input grows, and the length of everything the oracle adds to our input,
+/// minus `known_extra` bytes. Makes at most `MAX_BLOCK_SIZE + 1` queries.
+pub(crate) fn find_block_size<O: Oracle<Output = Vec<u8>>>(
+    oracle: &mut Counted<O>,
+    known_extra: usize,
+) -> Result<(usize, usize), AttackError> {
+    let base = oracle.query(&[])?.len();
+
+    for input_len in 1..=MAX_BLOCK_SIZE {
+        let len = oracle.query(&vec![b'A'; input_len])?.len();
+        if len > base {
+            // `input_len` bytes completed the last block, so the padding of
+            // the empty input was `input_len` bytes long.
+            let added = base
+                .checked_sub(input_len)
+                .and_then(|added| added.checked_sub(known_extra))
+                .ok_or(AttackError::UnexpectedAnswer)?;
+            return Ok((len - base, added));
+        }
+    }
+
+    Err(AttackError::BlockSizeNotFound)
+}
+
+/// Recovers the suffix byte by byte. For byte `i`, the reference ciphertext
+/// puts it last in a block after `block_size - 1` known bytes; every guess is
+/// a single block of the same known bytes and a candidate value, and the
+/// candidate whose ciphertext matches is the byte.
+pub(crate) fn recover_suffix<O, F>(
+    oracle: &mut Counted<O>,
+    layout: Layout,
+    mut progress: F,
+) -> Result<AttackReport, AttackError>
+where
+    O: Oracle<Output = Vec<u8>>,
+    F: FnMut(&Progress),
+{
+    let Layout {
+        block_size,
+        filler,
+        first_block,
+        suffix_len,
+    } = layout;
+    let max_queries = oracle.queries + layout.max_queries();
+    oracle.limit = oracle.limit.min(max_queries);
+
+    // one reference ciphertext per shift, keyed by the number of `A`s.
+    let mut references = HashMap::new();
+    let mut suffix: Vec<u8> = Vec::with_capacity(suffix_len);
+
+    for i in 0..suffix_len {
+        let shift = block_size - 1 - i % block_size;
+        if let Entry::Vacant(entry) = references.entry(shift) {
+            entry.insert(oracle.query(&vec![b'A'; filler + shift])?);
+        }
+        let block = first_block + i / block_size;
+        let reference = references[&shift]
+            .get(block * block_size..(block + 1) * block_size)
+            .ok_or(AttackError::UnexpectedAnswer)?;
+
+        // the block size minus one bytes before the unknown one: `A`s, then
+        // the end of what we recovered so far.
+        let mut known = vec![b'A'; block_size - 1];
+        known.extend_from_slice(&suffix);
+        let known = &known[known.len() - (block_size - 1)..];
+
+        let mut found = None;
+        for candidate in 0..=255u8 {
+            let mut input = vec![b'A'; filler];
+            input.extend_from_slice(known);
+            input.push(candidate);
+
+            let ciphertext = oracle.query(&input)?;
+            let start = first_block * block_size;
+            let guess = ciphertext
+                .get(start..start + block_size)
+                .ok_or(AttackError::UnexpectedAnswer)?;
+            if guess == reference {
+                found = Some(candidate);
+                break;
+            }
+        }
+
+        suffix.push(found.ok_or(AttackError::ByteNotFound(i))?);
+        progress(&Progress {
+            recovered: suffix.len(),
+            total: suffix_len,
+            queries: oracle.queries,
+            max_queries,
+        });
+    }
+
+    Ok(AttackReport {
+        block_size,
+        suffix,
+        queries: oracle.queries,
+        max_queries: Some(max_queries),
+    })
+}
+
+/// This function breaks the challenge 12 oracle and prints the suffix.
+pub fn byte_at_a_time_ecb_simple() {
+    let oracle = EcbSuffixOracle::challenge(&mut rand::thread_rng());
+    let report = byte_at_a_time(oracle, |progress| {
+        if progress.recovered % 32 == 0 || progress.recovered == progress.total {
+            println!(
+                "Recovered {}/{} bytes in {} queries",
+                progress.recovered, progress.total, progress.queries
+            );
+        }
+    })
+    .unwrap();
+
+    println!("Suffix: {}", String::from_utf8_lossy(&report.suffix));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut oracle = EcbSuffixOracle::challenge(&mut rng);
+
+        let mut updates = 0;
+        let report = byte_at_a_time(&mut oracle, |progress| {
+            updates += 1;
+            assert_eq!(progress.recovered, updates);
+            assert!(progress.queries <= progress.max_queries);
+        })
+        .unwrap();
+
+        let expected = base64_to_bytes(SECRET_SUFFIX).unwrap();
+        assert_eq!(report.block_size, 16);
+        assert_eq!(report.suffix, expected);
+        assert_eq!(updates, expected.len());
+        assert!(report.queries <= report.max_queries.unwrap());
+    }
+
+    #[test]
+    fn test_suffix_lengths() {
+        let mut rng = StdRng::seed_from_u64(12);
+
+        // suffixes shorter than, equal to and longer than a block.
+        for len in [0, 1, 15, 16, 17, 40] {
+            let suffix: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let oracle = EcbSuffixOracle::new(&mut rng, &suffix);
+
+            let report = byte_at_a_time(oracle, |_| {}).unwrap();
+            assert_eq!(report.suffix, suffix);
+        }
+    }
+
+    #[test]
+    fn test_not_ecb() {
+        use crate::chal2::aes_cbc_encrypt;
+
+        struct CbcOracle;
+        impl Oracle for CbcOracle {
+            type Output = Vec<u8>;
+
+            fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+                let mut plaintext = input.to_vec();
+                plaintext.extend_from_slice(b"secret");
+                Ok(aes_cbc_encrypt(&plaintext, b"YELLOW SUBMARINE", &[0; 16]).unwrap())
+            }
+        }
+
+        assert!(matches!(
+            byte_at_a_time(CbcOracle, |_| {}),
+            Err(AttackError::NotEcb)
+        ));
+    }
+
+    #[test]
+    fn test_unexpected_answers() {
+        // the empty input encrypts to nothing, which no padding allows.
+        let oracle = crate::oracle::from_fn(|input: &[u8]| vec![0u8; input.len().min(1) * 16]);
+        assert!(matches!(
+            byte_at_a_time(oracle, |_| {}),
+            Err(AttackError::UnexpectedAnswer)
+        ));
+
+        // answers get cut short halfway through the attack.
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut inner = EcbSuffixOracle::new(&mut rng, b"attack at dawn");
+        let mut queries = 0;
+        let oracle = crate::oracle::from_fn(|input: &[u8]| {
+            queries += 1;
+            let ciphertext = inner.query(input).unwrap();
+            if queries > 100 {
+                Vec::new()
+            } else {
+                ciphertext
+            }
+        });
+        assert!(matches!(
+            byte_at_a_time(oracle, |_| {}),
+            Err(AttackError::UnexpectedAnswer)
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+//! ECB cut-and-paste
+//!
+//! Profiles are encoded as `email=...&uid=10&role=user` and handed out
+//! encrypted with ECB. The encoding won't let `&` or `=` in, so we can't
+//! write `role=admin` ourselves, but ECB encrypts every block on its own:
+//! an email that ends the plaintext right after `role=` and another one that
+//! puts a padded `admin` block on a block boundary give two ciphertexts whose
+//! blocks can be spliced into a valid admin profile.
+
+use rand::Rng;
+use set1::{
+    aes::BLOCK_SIZE,
+    chal7::{aes_ecb_decrypt, aes_ecb_encrypt},
+    padding::{pkcs7_pad, Pkcs7},
+};
+
+use crate::{
+    chal3::random_aes_key,
+    oracle::{Oracle, OracleError},
+};
+
+/// What comes before the email in an encoded profile.
+const EMAIL_FIELD: &str = "email=";
+/// What comes between the email and the role in an encoded profile.
+const ROLE_FIELD: &str = "&uid=10&role=";
+
+/// This function encodes `key=value` pairs as `k=v&k=v`. Keys and values
+/// holding `&` or `=` are rejected rather than stripped, since they would
+/// change the structure of the result.
+///
+/// # Examples
+/// ```
+/// use set2::chal5::encode_kv;
+///
+/// assert_eq!(encode_kv(&[("foo", "bar"), ("baz", "qux")]).unwrap(), "foo=bar&baz=qux");
+/// assert!(encode_kv(&[("email", "foo@bar.com&role=admin")]).is_err());
+/// ```
+pub fn encode_kv(pairs: &[(&str, &str)]) -> Result<String, &'static str> {
+    let is_meta = |c: char| c == '&' || c == '=';
+
+    let mut fields = Vec::with_capacity(pairs.len());
+    for (key, value) in pairs {
+        if key.is_empty() {
+            return Err("Keys should not be empty");
+        }
+        if key.contains(is_meta) || value.contains(is_meta) {
+            return Err("Keys and values should not contain '&' or '='");
+        }
+        fields.push(format!("{}={}", key, value));
+    }
+
+    Ok(fields.join("&"))
+}
+
+/// This function parses `k=v&k=v` into its pairs, in order. Every field
+/// should hold exactly one `=` and a non-empty key.
+///
+/// # Examples
+/// ```
+/// use set2::chal5::parse_kv;
+///
+/// let pairs = parse_kv("foo=bar&baz=qux&zap=zazzle").unwrap();
+/// assert_eq!(pairs[2], ("zap".to_string(), "zazzle".to_string()));
+/// ```
+pub fn parse_kv(input: &str) -> Result<Vec<(String, String)>, &'static str> {
+    input
+        .split('&')
+        .map(|field| match field.split_once('=') {
+            Some((key, value)) if !key.is_empty() && !value.contains('=') => {
+                Ok((key.to_string(), value.to_string()))
+            }
+            _ => Err("Each field should be a key=value pair"),
+        })
+        .collect()
+}
+
+/// This function encodes the profile of a new user: the given email, uid 10
+/// and role `user`.
+pub fn profile_for(email: &str) -> Result<String, &'static str> {
+    encode_kv(&[("email", email), ("uid", "10"), ("role", "user")])
+}
+
+/// Hands out encrypted profiles and reads them back, with a key chosen at
+/// construction.
+pub struct ProfileService {
+    key: [u8; BLOCK_SIZE],
+}
+
+impl ProfileService {
+    /// Creates the service with a random key.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        ProfileService {
+            key: random_aes_key(rng),
+        }
+    }
+
+    /// Returns the encrypted profile for `email`.
+    pub fn encrypted_profile_for(&self, email: &str) -> Result<Vec<u8>, &'static str> {
+        aes_ecb_encrypt(profile_for(email)?.as_bytes(), &self.key, &Pkcs7)
+    }
+
+    /// Decrypts and parses a profile.
+    pub fn decrypt_profile(
+        &self,
+        ciphertext: &[u8],
+    ) -> Result<Vec<(String, String)>, &'static str> {
+        let plaintext = aes_ecb_decrypt(ciphertext, &self.key, &Pkcs7)?;
+        let plaintext =
+            String::from_utf8(plaintext).map_err(|_| "Profile should be valid UTF-8")?;
+
+        parse_kv(&plaintext)
+    }
+
+    /// Returns whether the profile decrypts to the `admin` role.
+    pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+        match self.decrypt_profile(ciphertext) {
+            Ok(profile) => profile
+                .iter()
+                .any(|(key, value)| key == "role" && value == "admin"),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Oracle for ProfileService {
+    type Output = Vec<u8>;
+
+    /// Takes an email and returns its encrypted profile.
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        let email = std::str::from_utf8(input)
+            .map_err(|_| OracleError::Rejected("Email should be valid UTF-8".to_string()))?;
+
+        self.encrypted_profile_for(email)
+            .map_err(|error| OracleError::Rejected(error.to_string()))
+    }
+}
+
+/// Returns `email` with just enough characters added that `role=` ends on a
+/// block boundary. They go in as a `+tag` before the `@`, so mail still goes
+/// to the same mailbox.
+fn align_email(email: &str) -> String {
+    let used = EMAIL_FIELD.len() + email.len() + ROLE_FIELD.len();
+    let missing = (BLOCK_SIZE - used % BLOCK_SIZE) % BLOCK_SIZE;
+    if missing == 0 {
+        return email.to_string();
+    }
+
+    let tag = format!("+{}", "a".repeat(missing - 1));
+    match email.find('@') {
+        Some(at) => format!("{}{}{}", &email[..at], tag, &email[at..]),
+        None => format!("{}{}", email, tag),
+    }
+}
+
+/// This function forges an admin profile for `email` from an oracle handing
+/// out encrypted profiles. The first query aligns the email so the profile
+/// ends with `role=` and a block of `user`; the second one fills the first
+/// block and follows with `admin` padded to a block on its own. Swapping the
+/// last block of the first ciphertext for that one gives `role=admin`.
+///
+/// # Examples
+/// ```
+/// use set2::chal5::{cut_and_paste, ProfileService};
+///
+/// let mut service = ProfileService::new(&mut rand::thread_rng());
+/// let forged = cut_and_paste(&mut service, "foo@bar.com").unwrap();
+///
+/// assert!(service.is_admin(&forged));
+/// ```
+pub fn cut_and_paste<O>(mut oracle: O, email: &str) -> Result<Vec<u8>, OracleError>
+where
+    O: Oracle<Output = Vec<u8>>,
+{
+    let aligned = oracle.query(align_email(email).as_bytes())?;
+
+    let filler = BLOCK_SIZE - EMAIL_FIELD.len() % BLOCK_SIZE;
+    let mut input = vec![b'a'; filler];
+    input.extend(pkcs7_pad(b"admin", BLOCK_SIZE).unwrap());
+    let admin = oracle.query(&input)?;
+
+    let admin_block = (EMAIL_FIELD.len() + filler) / BLOCK_SIZE;
+    let mut forged = aligned[..aligned.len() - BLOCK_SIZE].to_vec();
+    forged.extend_from_slice(&admin[admin_block * BLOCK_SIZE..(admin_block + 1) * BLOCK_SIZE]);
+
+    Ok(forged)
+}
+
+/// This function forges an admin profile and prints what it decrypts to.
+pub fn ecb_cut_and_paste() {
+    let mut service = ProfileService::new(&mut rand::thread_rng());
+    let forged = cut_and_paste(&mut service, "foo@bar.com").unwrap();
+
+    let profile = service.decrypt_profile(&forged).unwrap();
+    let fields: Vec<(&str, &str)> = profile
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    println!("Forged profile: {}", encode_kv(&fields).unwrap());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    //------ codec ------//
+
+    #[test]
+    fn test_kv_codec() {
+        let encoded = profile_for("foo@bar.com").unwrap();
+        assert_eq!(encoded, "email=foo@bar.com&uid=10&role=user");
+
+        let pairs = parse_kv(&encoded).unwrap();
+        let pairs: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [("email", "foo@bar.com"), ("uid", "10"), ("role", "user")]
+        );
+        assert_eq!(encode_kv(&pairs).unwrap(), encoded);
+    }
+
+    #[test]
+    fn test_kv_metacharacters() {
+        assert!(profile_for("foo@bar.com&role=admin").is_err());
+        assert!(profile_for("foo=bar").is_err());
+        assert!(encode_kv(&[("role&", "admin")]).is_err());
+        assert!(encode_kv(&[("", "admin")]).is_err());
+
+        assert!(parse_kv("foo").is_err());
+        assert!(parse_kv("foo=bar&").is_err());
+        assert!(parse_kv("=bar").is_err());
+        assert!(parse_kv("foo=bar=baz").is_err());
+        assert_eq!(
+            parse_kv("foo=").unwrap(),
+            [("foo".to_string(), String::new())]
+        );
+    }
+
+    //------ service ------//
+
+    #[test]
+    fn test_profile_service() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let service = ProfileService::new(&mut rng);
+
+        let ciphertext = service.encrypted_profile_for("foo@bar.com").unwrap();
+        assert_eq!(ciphertext.len() % BLOCK_SIZE, 0);
+        assert!(!service.is_admin(&ciphertext));
+        assert_eq!(service.decrypt_profile(&ciphertext).unwrap()[2].1, "user");
+
+        assert!(service.encrypted_profile_for("a&role=admin").is_err());
+        assert!(service.decrypt_profile(&ciphertext[..16]).is_err());
+    }
+
+    //------ attack ------//
+
+    #[test]
+    fn test_cut_and_paste() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut service = ProfileService::new(&mut rng);
+
+        // every alignment, with and without an `@`.
+        for len in 0..=2 * BLOCK_SIZE {
+            for email in [format!("{}@bar.com", "f".repeat(len)), "f".repeat(len)] {
+                let forged = cut_and_paste(&mut service, &email).unwrap();
+                assert!(service.is_admin(&forged), "email {:?}", email);
+
+                let profile = service.decrypt_profile(&forged).unwrap();
+                assert_eq!(profile.len(), 3);
+                assert_eq!(profile[0].1, align_email(&email));
+                assert_eq!(profile[1].1, "10");
+            }
+        }
+    }
+
+    #[test]
+    fn test_align_email() {
+        assert_eq!(align_email("foo@bar.com"), "foo+a@bar.com");
+        for len in 0..40 {
+            let email = align_email(&"f".repeat(len));
+            assert_eq!(
+                (EMAIL_FIELD.len() + email.len() + ROLE_FIELD.len()) % BLOCK_SIZE,
+                0
+            );
+        }
+    }
+}
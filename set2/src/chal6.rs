@@ -0,0 +1,442 @@
+//! Byte-at-a-time ECB decryption (Harder)
+//!
+//! Same as challenge 12, but the oracle puts some random bytes before our
+//! input. Two copies of a marker block only encrypt to two equal blocks when
+//! they start on a block boundary, so growing the input until that happens
+//! tells us how long the prefix is. After that our input is padded to the
+//! next block and the simple attack runs unchanged.
+//!
+//! When the prefix length changes on every call, there is no fixed boundary.
+//! Every query then goes out behind the marker pair and is sent again until
+//! the pair shows up aligned, which happens once every block size calls.
+
+use rand::Rng;
+use set1::{chal7::aes_ecb_encrypt, chal8::duplicate_checker, padding::Pkcs7};
+
+use crate::{
+    chal3::{random_aes_key, random_bytes},
+    chal4::{
+        byte_at_a_time, find_block_size, recover_suffix, AttackError, AttackReport, Counted,
+        Layout, Progress, MAX_BLOCK_SIZE,
+    },
+    oracle::{Oracle, OracleError},
+};
+
+/// How many times a query is sent before giving up on aligning the markers.
+pub const MAX_ATTEMPTS: usize = 1000;
+
+/// How many times the same probe is sent to tell a fixed prefix from one
+/// that changes. A varying prefix can repeat its length now and then, but
+/// it is unlikely to do so this many times in a row.
+const PROBES: usize = 6;
+
+/// The challenge 14 oracle: AES-128-ECB of `prefix || input || suffix`
+/// under a key and prefix chosen at construction.
+pub struct PrefixEcbOracle {
+    key: [u8; 16],
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl PrefixEcbOracle {
+    /// Creates the oracle with a random key and the given prefix and suffix.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, prefix: &[u8], suffix: &[u8]) -> Self {
+        PrefixEcbOracle {
+            key: random_aes_key(rng),
+            prefix: prefix.to_vec(),
+            suffix: suffix.to_vec(),
+        }
+    }
+
+    /// Creates the oracle with a random key, a prefix of 0 to 64 random bytes
+    /// and the given suffix.
+    pub fn random<R: Rng + ?Sized>(rng: &mut R, suffix: &[u8]) -> Self {
+        let prefix_len = rng.gen_range(0..=64);
+        let prefix = random_bytes(rng, prefix_len);
+        PrefixEcbOracle::new(rng, &prefix, suffix)
+    }
+}
+
+impl Oracle for PrefixEcbOracle {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        let mut plaintext = self.prefix.clone();
+        plaintext.extend_from_slice(input);
+        plaintext.extend_from_slice(&self.suffix);
+
+        aes_ecb_encrypt(&plaintext, &self.key, &Pkcs7)
+            .map_err(|error| OracleError::Rejected(error.to_string()))
+    }
+}
+
+/// Like `PrefixEcbOracle`, but a new random prefix of 0 to `max_prefix`
+/// bytes is drawn for every query.
+pub struct VaryingPrefixOracle<R> {
+    rng: R,
+    key: [u8; 16],
+    max_prefix: usize,
+    suffix: Vec<u8>,
+}
+
+impl<R: Rng> VaryingPrefixOracle<R> {
+    /// Creates the oracle, taking a key from `rng` and keeping it for the
+    /// prefixes.
+    pub fn new(mut rng: R, max_prefix: usize, suffix: &[u8]) -> Self {
+        VaryingPrefixOracle {
+            key: random_aes_key(&mut rng),
+            rng,
+            max_prefix,
+            suffix: suffix.to_vec(),
+        }
+    }
+}
+
+impl<R: Rng> Oracle for VaryingPrefixOracle<R> {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        let prefix_len = self.rng.gen_range(0..=self.max_prefix);
+        let mut plaintext = random_bytes(&mut self.rng, prefix_len);
+        plaintext.extend_from_slice(input);
+        plaintext.extend_from_slice(&self.suffix);
+
+        aes_ecb_encrypt(&plaintext, &self.key, &Pkcs7)
+            .map_err(|error| OracleError::Rejected(error.to_string()))
+    }
+}
+
+/// Returns a block with all bytes different, so no rotation of it is equal
+/// to it and two copies only give equal blocks when they are aligned. Two
+/// `flip` values give two unrelated markers.
+fn marker(block_size: usize, flip: u8) -> Vec<u8> {
+    (0..block_size).map(|i| flip ^ i as u8).collect()
+}
+
+/// Returns the indices of the blocks equal to the one after them.
+fn pairs(ciphertext: &[u8], block_size: usize) -> Vec<usize> {
+    if !duplicate_checker(ciphertext, block_size) {
+        return Vec::new();
+    }
+
+    let blocks: Vec<&[u8]> = ciphertext.chunks_exact(block_size).collect();
+    (0..blocks.len().saturating_sub(1))
+        .filter(|&i| blocks[i] == blocks[i + 1])
+        .collect()
+}
+
+/// Hides a prefix whose length changes on every call. Each query is sent
+/// behind two marker blocks until they come back as two equal, aligned
+/// ciphertext blocks, and everything up to them is cut off, so what's left
+/// is the encryption of `input || suffix` alone.
+pub struct AlignedOracle<O> {
+    oracle: O,
+    block_size: usize,
+    marker: Vec<u8>,
+    encrypted_marker: Vec<u8>,
+    queries: usize,
+}
+
+impl<O: Oracle<Output = Vec<u8>>> AlignedOracle<O> {
+    /// Wraps `oracle`, sending the marker pair on its own until it shows up
+    /// aligned to learn what it encrypts to.
+    pub fn new(mut oracle: O, block_size: usize) -> Result<Self, OracleError> {
+        let marker = marker(block_size, 0x80);
+        let pair = marker.repeat(2);
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let ciphertext = oracle.query(&pair)?;
+            if let Some(&index) = pairs(&ciphertext, block_size).first() {
+                let start = index * block_size;
+                return Ok(AlignedOracle {
+                    oracle,
+                    block_size,
+                    marker,
+                    encrypted_marker: ciphertext[start..start + block_size].to_vec(),
+                    queries: attempt,
+                });
+            }
+        }
+
+        Err(OracleError::Rejected(
+            "The marker blocks never came back aligned".to_string(),
+        ))
+    }
+
+    /// Queries sent to the wrapped oracle so far, retries included.
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+}
+
+impl<O: Oracle<Output = Vec<u8>>> Oracle for AlignedOracle<O> {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        let mut framed = self.marker.repeat(2);
+        framed.extend_from_slice(input);
+
+        for _ in 0..MAX_ATTEMPTS {
+            self.queries += 1;
+            let ciphertext = self.oracle.query(&framed)?;
+
+            // our input can hold equal blocks too, so look for the marker
+            // itself rather than any pair.
+            let blocks: Vec<&[u8]> = ciphertext.chunks_exact(self.block_size).collect();
+            let aligned = blocks
+                .windows(2)
+                .position(|pair| pair[0] == self.encrypted_marker && pair[1] == pair[0]);
+            if let Some(index) = aligned {
+                return Ok(ciphertext[(index + 2) * self.block_size..].to_vec());
+            }
+        }
+
+        Err(OracleError::Rejected(
+            "The marker blocks never came back aligned".to_string(),
+        ))
+    }
+}
+
+/// Finds the prefix length of a deterministic oracle: the input grows by one
+/// byte until the marker pair after it comes back as two equal blocks. The
+/// prefix, our filler or the suffix can hold equal blocks of their own, so
+/// each length is tried with two different markers and only a pair at the
+/// same place in both, that encrypts differently, counts.
+fn find_prefix_len<O: Oracle<Output = Vec<u8>>>(
+    oracle: &mut Counted<O>,
+    block_size: usize,
+) -> Result<usize, AttackError> {
+    let block = |ciphertext: &[u8], index: usize| {
+        ciphertext[index * block_size..(index + 1) * block_size].to_vec()
+    };
+
+    for filler in 0..block_size {
+        let mut responses = Vec::with_capacity(2);
+        for flip in [0x80, 0xc0] {
+            let mut input = vec![b'A'; filler];
+            input.extend_from_slice(&marker(block_size, flip).repeat(2));
+            responses.push(oracle.query(&input)?);
+        }
+
+        let (first, second) = (&responses[0], &responses[1]);
+        let second_pairs = pairs(second, block_size);
+        let aligned = pairs(first, block_size).into_iter().find(|index| {
+            second_pairs.contains(index) && block(first, *index) != block(second, *index)
+        });
+        if let Some(index) = aligned {
+            return (index * block_size)
+                .checked_sub(filler)
+                .ok_or(AttackError::UnexpectedAnswer);
+        }
+    }
+
+    Err(AttackError::NotEcb)
+}
+
+/// Finds the block size of an oracle whose ciphertext lengths are noisy, as
+/// the largest number all of them are a multiple of.
+fn noisy_block_size<O: Oracle<Output = Vec<u8>>>(
+    oracle: &mut Counted<O>,
+) -> Result<usize, AttackError> {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    let mut block_size = 0;
+    for input_len in 0..=MAX_BLOCK_SIZE {
+        block_size = gcd(block_size, oracle.query(&vec![b'A'; input_len])?.len());
+    }
+
+    if block_size <= 1 || block_size > MAX_BLOCK_SIZE {
+        return Err(AttackError::BlockSizeNotFound);
+    }
+    Ok(block_size)
+}
+
+/// This function runs the byte-at-a-time attack against an ECB oracle that
+/// puts unknown bytes before our input and a secret after it.
+///
+/// If the same input gives the same ciphertext every time, the prefix is
+/// fixed: its length is found with marker blocks and the input is padded to
+/// the next block, costing at most two block sizes of queries on top of the
+/// simple attack. Otherwise every query is retried behind the markers
+/// through an `AlignedOracle`, which costs about a block size of queries
+/// each, and no `max_queries` is reported. Either way, the
+/// reported count includes every query sent to `oracle`, while `progress`
+/// sees the queries made once the prefix is dealt with.
+///
+/// # Examples
+/// ```
+/// use set2::{chal4::EcbSuffixOracle, chal6::{byte_at_a_time_with_prefix, PrefixEcbOracle}};
+///
+/// let mut rng = rand::thread_rng();
+/// let oracle = PrefixEcbOracle::new(&mut rng, b"some random bytes", b"attack at dawn");
+/// let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+///
+/// assert_eq!(report.suffix, b"attack at dawn");
+/// ```
+pub fn byte_at_a_time_with_prefix<O, F>(oracle: O, progress: F) -> Result<AttackReport, AttackError>
+where
+    O: Oracle<Output = Vec<u8>>,
+    F: FnMut(&Progress),
+{
+    let mut oracle = Counted::new(oracle);
+
+    let probe = vec![b'A'; 3 * MAX_BLOCK_SIZE];
+    let first = oracle.query(&probe)?;
+    for _ in 1..PROBES {
+        if oracle.query(&probe)? != first {
+            return varying_prefix(oracle, progress);
+        }
+    }
+
+    let (block_size, added) = find_block_size(&mut oracle, 0)?;
+    let prefix_len = find_prefix_len(&mut oracle, block_size)?;
+
+    let layout = Layout {
+        block_size,
+        filler: (block_size - prefix_len % block_size) % block_size,
+        first_block: (prefix_len + block_size - 1) / block_size,
+        suffix_len: added
+            .checked_sub(prefix_len)
+            .ok_or(AttackError::UnexpectedAnswer)?,
+    };
+    recover_suffix(&mut oracle, layout, progress)
+}
+
+/// Runs the simple attack through an `AlignedOracle`.
+fn varying_prefix<O, F>(mut oracle: Counted<O>, progress: F) -> Result<AttackReport, AttackError>
+where
+    O: Oracle<Output = Vec<u8>>,
+    F: FnMut(&Progress),
+{
+    let block_size = noisy_block_size(&mut oracle)?;
+    let detection = oracle.queries;
+
+    let mut aligned = AlignedOracle::new(oracle.oracle, block_size)?;
+    let mut report = byte_at_a_time(&mut aligned, progress)?;
+
+    // retries have no useful bound, so neither has the whole attack.
+    report.queries = detection + aligned.queries();
+    report.max_queries = None;
+    Ok(report)
+}
+
+/// This function breaks the challenge 14 oracle and prints the suffix.
+pub fn byte_at_a_time_ecb_harder() {
+    let mut rng = rand::thread_rng();
+    let suffix = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow\n";
+    let oracle = PrefixEcbOracle::random(&mut rng, suffix);
+
+    let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+
+    println!(
+        "Recovered {} bytes in {} queries: {}",
+        report.suffix.len(),
+        report.queries,
+        String::from_utf8_lossy(&report.suffix)
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    //------ fixed prefix ------//
+
+    #[test]
+    fn test_prefix_lengths() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let suffix = b"Rollin' in my 5.0";
+
+        for prefix_len in [0, 1, 5, 15, 16, 17, 33] {
+            let prefix = random_bytes(&mut rng, prefix_len);
+            let oracle = PrefixEcbOracle::new(&mut rng, &prefix, suffix);
+
+            let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+            assert_eq!(report.block_size, 16);
+            assert_eq!(report.suffix, suffix);
+            assert!(report.queries <= report.max_queries.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_prefix_looks_like_input() {
+        let mut rng = StdRng::seed_from_u64(14);
+
+        // a prefix ending with our filler bytes and a suffix starting with
+        // them must not move the boundary.
+        let prefix = [b'A'; 21];
+        let suffix = b"AAAAAAAAAAAAAAAAAAAA secret";
+        let oracle = PrefixEcbOracle::new(&mut rng, &prefix, suffix);
+
+        let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+        assert_eq!(report.suffix, suffix);
+    }
+
+    #[test]
+    fn test_random_prefix() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let suffix = b"With my rag-top down so my hair can blow";
+        let oracle = PrefixEcbOracle::random(&mut rng, suffix);
+
+        let mut updates = 0;
+        let report = byte_at_a_time_with_prefix(oracle, |_| updates += 1).unwrap();
+        assert_eq!(report.suffix, suffix);
+        assert_eq!(updates, suffix.len());
+    }
+
+    //------ varying prefix ------//
+
+    #[test]
+    fn test_varying_prefix() {
+        let rng = StdRng::seed_from_u64(14);
+        let suffix = b"Did you stop?";
+        let oracle = VaryingPrefixOracle::new(rng, 40, suffix);
+
+        let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+        assert_eq!(report.block_size, 16);
+        assert_eq!(report.suffix, suffix);
+        assert_eq!(report.max_queries, None);
+    }
+
+    #[test]
+    fn test_varying_prefix_repeats_length() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let key = random_aes_key(&mut rng);
+        let suffix = b"No, I just drove by";
+
+        // the first probes both see an empty prefix.
+        let mut calls = 0;
+        let oracle = crate::oracle::from_fn(move |input: &[u8]| {
+            calls += 1;
+            let prefix_len = if calls <= 2 { 0 } else { rng.gen_range(0..=40) };
+            let mut plaintext = random_bytes(&mut rng, prefix_len);
+            plaintext.extend_from_slice(input);
+            plaintext.extend_from_slice(suffix);
+            aes_ecb_encrypt(&plaintext, &key, &Pkcs7).unwrap()
+        });
+
+        let report = byte_at_a_time_with_prefix(oracle, |_| {}).unwrap();
+        assert_eq!(report.suffix, suffix);
+    }
+
+    #[test]
+    fn test_aligned_oracle() {
+        let rng = StdRng::seed_from_u64(14);
+        // the oracle takes its key first, so a copy of the generator gives it.
+        let key = random_aes_key(&mut rng.clone());
+        let varying = VaryingPrefixOracle::new(rng, 40, b"secret");
+        let mut oracle = AlignedOracle::new(varying, 16).unwrap();
+
+        let ciphertext = oracle.query(b"YELLOW SUBMARINE").unwrap();
+        let expected = aes_ecb_encrypt(b"YELLOW SUBMARINEsecret", &key, &Pkcs7).unwrap();
+        assert_eq!(ciphertext, expected);
+        assert!(oracle.queries() >= 2);
+    }
+}
@@ -0,0 +1,163 @@
+//! CBC bitflipping attacks
+//!
+//! The service wraps user data in a cookie-like string and encrypts it with
+//! CBC, quoting `;` and `=` so we can't write `;admin=true;` ourselves. In
+//! CBC every plaintext block is XORed with the previous ciphertext block, so
+//! flipping a bit in one ciphertext block flips the same bit in the next
+//! plaintext block. The block we flip decrypts to garbage, but the one after
+//! it says whatever we want.
+
+use rand::Rng;
+use set1::{
+    aes::BLOCK_SIZE,
+    malleability::{edit_ciphertext, EditMode},
+};
+
+use crate::{
+    chal2::{aes_cbc_decrypt, aes_cbc_encrypt},
+    chal3::{random_aes_key, random_bytes},
+    oracle::{Oracle, OracleError},
+};
+
+/// What the service puts before the user data.
+const PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+/// What the service puts after the user data.
+const SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+/// What we want the decrypted cookie to hold.
+const TARGET: &[u8] = b";admin=true;";
+
+/// Encrypts user data into a cookie with AES-128-CBC, under a key and IV
+/// chosen at construction.
+pub struct CommentService {
+    key: [u8; BLOCK_SIZE],
+    iv: Vec<u8>,
+}
+
+impl CommentService {
+    /// Creates the service with a random key and IV.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        CommentService {
+            key: random_aes_key(rng),
+            iv: random_bytes(rng, BLOCK_SIZE),
+        }
+    }
+
+    /// Returns the encrypted cookie for `userdata`, after quoting `;` and `=`
+    /// as `%3B` and `%3D`.
+    pub fn encrypt_userdata(&self, userdata: &[u8]) -> Vec<u8> {
+        let mut plaintext = PREFIX.to_vec();
+        for &byte in userdata {
+            match byte {
+                b';' => plaintext.extend_from_slice(b"%3B"),
+                b'=' => plaintext.extend_from_slice(b"%3D"),
+                _ => plaintext.push(byte),
+            }
+        }
+        plaintext.extend_from_slice(SUFFIX);
+
+        aes_cbc_encrypt(&plaintext, &self.key, &self.iv).unwrap()
+    }
+
+    /// Decrypts a cookie and returns whether one of its `;` separated fields
+    /// is `admin=true`. The fields are matched as bytes, since a flipped
+    /// block decrypts to anything.
+    pub fn is_admin(&self, ciphertext: &[u8]) -> Result<bool, &'static str> {
+        let plaintext = aes_cbc_decrypt(ciphertext, &self.key, &self.iv)?;
+
+        Ok(plaintext
+            .split(|&byte| byte == b';')
+            .any(|field| field == b"admin=true"))
+    }
+}
+
+impl Oracle for CommentService {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        Ok(self.encrypt_userdata(input))
+    }
+}
+
+/// This function forges a cookie holding `;admin=true;`. Our input is filler
+/// up to the next block boundary, a whole block to sacrifice and then
+/// `TARGET` with every byte replaced by `A`. Editing those `A`s into `TARGET`
+/// scrambles the sacrificed block, which nothing reads.
+///
+/// # Examples
+/// ```
+/// use set2::chal8::{bitflip_admin, CommentService};
+///
+/// let mut service = CommentService::new(&mut rand::thread_rng());
+/// let forged = bitflip_admin(&mut service).unwrap();
+///
+/// assert!(service.is_admin(&forged).unwrap());
+/// ```
+pub fn bitflip_admin<O>(mut oracle: O) -> Result<Vec<u8>, OracleError>
+where
+    O: Oracle<Output = Vec<u8>>,
+{
+    let filler = (BLOCK_SIZE - PREFIX.len() % BLOCK_SIZE) % BLOCK_SIZE;
+    let placeholder = vec![b'A'; TARGET.len()];
+
+    let mut input = vec![b'A'; filler + BLOCK_SIZE];
+    input.extend_from_slice(&placeholder);
+    let ciphertext = oracle.query(&input)?;
+
+    let mode = EditMode::Cbc {
+        block_size: BLOCK_SIZE,
+        iv_prefixed: false,
+    };
+    let offset = PREFIX.len() + filler + BLOCK_SIZE;
+    let edited = edit_ciphertext(&ciphertext, mode, offset, &placeholder, TARGET)
+        .map_err(|error| OracleError::Rejected(error.to_string()))?;
+
+    Ok(edited.ciphertext)
+}
+
+/// This function forges an admin cookie and prints whether the service
+/// accepts it.
+pub fn cbc_bitflipping_attacks() {
+    let mut service = CommentService::new(&mut rand::thread_rng());
+    let forged = bitflip_admin(&mut service).unwrap();
+
+    println!(
+        "Forged admin cookie: {}",
+        service.is_admin(&forged).unwrap()
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_quoting() {
+        let mut rng = StdRng::seed_from_u64(16);
+        let service = CommentService::new(&mut rng);
+
+        let ciphertext = service.encrypt_userdata(b";admin=true;");
+        assert!(!service.is_admin(&ciphertext).unwrap());
+
+        let plaintext = aes_cbc_decrypt(&ciphertext, &service.key, &service.iv).unwrap();
+        assert_eq!(
+            &plaintext[PREFIX.len()..plaintext.len() - SUFFIX.len()],
+            b"%3Badmin%3Dtrue%3B"
+        );
+    }
+
+    #[test]
+    fn test_bitflip_admin() {
+        let mut rng = StdRng::seed_from_u64(16);
+
+        for _ in 0..20 {
+            let mut service = CommentService::new(&mut rng);
+            let honest = service.encrypt_userdata(&[b'A'; 28]);
+            assert!(!service.is_admin(&honest).unwrap());
+
+            let forged = bitflip_admin(&mut service).unwrap();
+            assert!(service.is_admin(&forged).unwrap());
+        }
+    }
+}
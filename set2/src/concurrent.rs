@@ -0,0 +1,219 @@
+//! Querying an oracle from several threads
+//!
+//! Against a network oracle most of an attack is spent waiting for answers.
+//! Guesses that do not depend on each other, like the 256 values of a byte,
+//! can be in flight together: `Concurrent` sends a batch of queries from up
+//! to `parallelism` threads, each with its own clone of the oracle, and hands
+//! the answers back in the order of the inputs. An attack that reads them in
+//! that order decides exactly as it would querying one at a time.
+
+use crate::oracle::{Oracle, OracleError};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// An oracle that can also answer a batch of queries concurrently. Single
+/// queries go to the wrapped oracle as usual.
+///
+/// # Examples
+/// ```
+/// use set2::{concurrent::Concurrent, oracle::from_fn};
+///
+/// let mut oracle = Concurrent::new(from_fn(|input: &[u8]| input.len()), 4);
+/// let inputs: Vec<Vec<u8>> = (0..10).map(|len| vec![0; len]).collect();
+///
+/// let answers = oracle.query_all(&inputs).unwrap();
+/// assert_eq!(answers, (0..10).collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Concurrent<O> {
+    oracle: O,
+    parallelism: usize,
+}
+
+impl<O> Concurrent<O>
+where
+    O: Oracle + Clone + Send,
+    O::Output: Send,
+{
+    /// Runs at most `parallelism` queries at once; zero counts as one.
+    pub fn new(oracle: O, parallelism: usize) -> Self {
+        Concurrent {
+            oracle,
+            parallelism: parallelism.max(1),
+        }
+    }
+
+    pub fn parallelism(&self) -> usize {
+        self.parallelism
+    }
+
+    /// Sends every input and returns the answers in the order of `inputs`.
+    /// Inputs are handed out in order, and once a query fails no new ones
+    /// start, so the error returned is the one of the first failing input,
+    /// as it would be sequentially.
+    pub fn query_all(&mut self, inputs: &[Vec<u8>]) -> Result<Vec<O::Output>, OracleError> {
+        if self.parallelism == 1 || inputs.len() <= 1 {
+            return inputs
+                .iter()
+                .map(|input| self.oracle.query(input))
+                .collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(inputs.len()) {
+                let mut oracle = self.oracle.clone();
+                let sender = sender.clone();
+                let (next, failed) = (&next, &failed);
+
+                scope.spawn(move || {
+                    while !failed.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(input) = inputs.get(index) else {
+                            break;
+                        };
+
+                        let answer = oracle.query(input);
+                        if answer.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        // the receiver outlives the scope.
+                        sender.send((index, answer)).unwrap();
+                    }
+                });
+            }
+        });
+        drop(sender);
+
+        let mut answers: Vec<Option<Result<O::Output, OracleError>>> =
+            inputs.iter().map(|_| None).collect();
+        for (index, answer) in receiver {
+            answers[index] = Some(answer);
+        }
+
+        // every input before the first failure was answered.
+        answers.into_iter().map_while(|answer| answer).collect()
+    }
+
+    pub fn into_inner(self) -> O {
+        self.oracle
+    }
+}
+
+impl<O: Oracle> Oracle for Concurrent<O> {
+    type Output = O::Output;
+
+    fn query(&mut self, input: &[u8]) -> Result<O::Output, OracleError> {
+        self.oracle.query(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        oracle::from_fn,
+        transport::{serve_lines, TcpOracle},
+    };
+    use std::{net::TcpListener, sync::Arc, time::Duration};
+
+    fn inputs(count: u8) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|byte| vec![byte; byte as usize % 5])
+            .collect()
+    }
+
+    #[test]
+    fn test_same_as_sequential() {
+        let oracle = from_fn(|input: &[u8]| {
+            thread::sleep(Duration::from_millis(input.len() as u64));
+            input.iter().map(|byte| byte ^ 0x5a).collect::<Vec<u8>>()
+        });
+        let inputs = inputs(64);
+
+        let mut sequential = oracle.clone();
+        let expected: Vec<Vec<u8>> = inputs
+            .iter()
+            .map(|input| sequential.query(input).unwrap())
+            .collect();
+        for parallelism in [0, 1, 3, 16, 100] {
+            let mut concurrent = Concurrent::new(oracle.clone(), parallelism);
+            assert_eq!(concurrent.query_all(&inputs).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_first_error() {
+        let mut oracle = Concurrent::new(Rejecting { reject_from: 20 }, 8);
+        let inputs: Vec<Vec<u8>> = (0..64).map(|byte| vec![byte]).collect();
+
+        match oracle.query_all(&inputs) {
+            Err(OracleError::Rejected(message)) => assert_eq!(message, "20"),
+            other => panic!("expected the first rejection, got {:?}", other),
+        }
+        assert!(oracle.query_all(&inputs[..20]).is_ok());
+    }
+
+    /// Rejects every input whose first byte is at least `reject_from`.
+    #[derive(Clone)]
+    struct Rejecting {
+        reject_from: u8,
+    }
+
+    impl Oracle for Rejecting {
+        type Output = ();
+
+        fn query(&mut self, input: &[u8]) -> Result<(), OracleError> {
+            // later inputs fail faster, so a wrong pick would show.
+            thread::sleep(Duration::from_millis(64 - input[0] as u64));
+            if input[0] >= self.reject_from {
+                return Err(OracleError::Rejected(input[0].to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_overlapping_latency() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // the server keeps the most queries it had in flight at once.
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (counter, most) = (Arc::clone(&in_flight), Arc::clone(&peak));
+        thread::spawn(move || {
+            serve_lines(listener, move |input| {
+                let now = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                counter.fetch_sub(1, Ordering::SeqCst);
+                Ok(input.to_vec())
+            })
+        });
+
+        // a TCP oracle keeps its connection, so every thread connects anew.
+        #[derive(Clone)]
+        struct Reconnecting(std::net::SocketAddr);
+        impl Oracle for Reconnecting {
+            type Output = Vec<u8>;
+            fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+                TcpOracle::connect(self.0)?.query(input)
+            }
+        }
+
+        let inputs = inputs(16);
+        let mut oracle = Concurrent::new(Reconnecting(addr), 16);
+        assert_eq!(oracle.query_all(&inputs).unwrap(), inputs);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+        assert!(peak.load(Ordering::SeqCst) <= 16);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+}
@@ -1 +1,11 @@
-mod chal1;
+pub mod chal1;
+pub mod chal2;
+pub mod chal3;
+pub mod chal4;
+pub mod chal5;
+pub mod chal6;
+pub mod chal8;
+pub mod concurrent;
+pub mod oracle;
+pub mod session;
+pub mod transport;
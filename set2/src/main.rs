@@ -0,0 +1,13 @@
+use set2::{
+    chal2::cbc_mode, chal3::ecb_cbc_detection_oracle, chal4::byte_at_a_time_ecb_simple,
+    chal5::ecb_cut_and_paste, chal6::byte_at_a_time_ecb_harder, chal8::cbc_bitflipping_attacks,
+};
+
+fn main() {
+    cbc_mode();
+    ecb_cbc_detection_oracle();
+    byte_at_a_time_ecb_simple();
+    ecb_cut_and_paste();
+    byte_at_a_time_ecb_harder();
+    cbc_bitflipping_attacks();
+}
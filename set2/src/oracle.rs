@@ -0,0 +1,145 @@
+//! Oracles the attacks run against
+//!
+//! An oracle takes attacker-chosen bytes and answers with something derived
+//! from them: a ciphertext, or whether a ciphertext decrypted to valid
+//! padding. The attacks only see this trait, so the same code works against
+//! an in-process target, a network service or a recorded session.
+
+use std::{fmt, io};
+
+/// Something that answers queries with attacker-chosen input.
+pub trait Oracle {
+    /// What a query returns, e.g. `Vec<u8>` for an encryption oracle.
+    type Output;
+
+    /// Sends `input` to the oracle and returns its answer.
+    fn query(&mut self, input: &[u8]) -> Result<Self::Output, OracleError>;
+
+    /// Returns an oracle that passes every answer through `f`, e.g. to turn
+    /// the bytes a network oracle returns into a `bool`.
+    fn map<F, T>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Output) -> T,
+    {
+        Map { oracle: self, f }
+    }
+}
+
+impl<O: Oracle + ?Sized> Oracle for &mut O {
+    type Output = O::Output;
+
+    fn query(&mut self, input: &[u8]) -> Result<Self::Output, OracleError> {
+        (**self).query(input)
+    }
+}
+
+/// An oracle answering with a Rust closure, see `from_fn`.
+#[derive(Clone)]
+pub struct FnOracle<F>(F);
+
+/// Returns an oracle that calls `f` on every query. This is the in-process
+/// transport: targets written as plain functions need nothing else.
+///
+/// # Examples
+/// ```
+/// use set2::oracle::{from_fn, Oracle};
+///
+/// let mut oracle = from_fn(|input: &[u8]| input.len());
+/// assert_eq!(oracle.query(b"YELLOW SUBMARINE").unwrap(), 16);
+/// ```
+pub fn from_fn<F, T>(f: F) -> FnOracle<F>
+where
+    F: FnMut(&[u8]) -> T,
+{
+    FnOracle(f)
+}
+
+impl<F, T> Oracle for FnOracle<F>
+where
+    F: FnMut(&[u8]) -> T,
+{
+    type Output = T;
+
+    fn query(&mut self, input: &[u8]) -> Result<T, OracleError> {
+        Ok((self.0)(input))
+    }
+}
+
+/// An oracle whose answers go through a function, see `Oracle::map`.
+#[derive(Clone)]
+pub struct Map<O, F> {
+    oracle: O,
+    f: F,
+}
+
+impl<O, F, T> Oracle for Map<O, F>
+where
+    O: Oracle,
+    F: FnMut(O::Output) -> T,
+{
+    type Output = T;
+
+    fn query(&mut self, input: &[u8]) -> Result<T, OracleError> {
+        self.oracle.query(input).map(&mut self.f)
+    }
+}
+
+/// Why an oracle could not answer.
+#[derive(Debug)]
+pub enum OracleError {
+    /// Talking to the oracle failed.
+    Io(io::Error),
+    /// The oracle refused the input or answered something unexpected.
+    Rejected(String),
+    /// The query budget, the number given, was spent.
+    BudgetExhausted(usize),
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OracleError::Io(error) => write!(f, "Oracle I/O error: {}", error),
+            OracleError::Rejected(reason) => write!(f, "Oracle rejected the query: {}", reason),
+            OracleError::BudgetExhausted(budget) => {
+                write!(f, "The budget of {} queries is spent", budget)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OracleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OracleError::Io(error) => Some(error),
+            OracleError::Rejected(_) | OracleError::BudgetExhausted(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for OracleError {
+    fn from(error: io::Error) -> Self {
+        OracleError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_closure_oracle() {
+        let mut calls = 0;
+        {
+            let mut oracle = from_fn(|input: &[u8]| {
+                calls += 1;
+                input.to_vec()
+            })
+            .map(|output| output.len());
+
+            assert_eq!(oracle.query(b"abc").unwrap(), 3);
+            assert_eq!(oracle.query(b"").unwrap(), 0);
+        }
+        assert_eq!(calls, 2);
+    }
+}
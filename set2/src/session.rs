@@ -0,0 +1,384 @@
+//! Recording, replaying and metering oracle sessions
+//!
+//! `Recorder` writes every query and answer of an oracle to a file, one line
+//! each, and `Replay` reads such a file back and answers the same queries in
+//! the same order without the original oracle, which makes a failing run easy
+//! to step through. `Metered` counts queries, stops at a budget and reports
+//! statistics to compare attacks by.
+
+use crate::oracle::{Oracle, OracleError};
+use set1::{chal1::hex_to_bytes, chal5::bytes_to_hex};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{BufRead, Write},
+    time::{Duration, Instant},
+};
+
+/// Oracle answers that can be written to a recording and read back.
+pub trait Recordable: Sized {
+    fn to_record(&self) -> Vec<u8>;
+
+    /// Returns `None` if `bytes` is not a recorded answer.
+    fn from_record(bytes: &[u8]) -> Option<Self>;
+}
+
+impl Recordable for Vec<u8> {
+    fn to_record(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_record(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl Recordable for bool {
+    fn to_record(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    fn from_record(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// An oracle that writes every query and its answer to `writer`, as a line
+/// with the hex of both separated by a space. A query the oracle fails is
+/// written as its hex and `err` with the error message.
+pub struct Recorder<O, W> {
+    oracle: O,
+    writer: W,
+}
+
+impl<O, W> Recorder<O, W>
+where
+    O: Oracle,
+    O::Output: Recordable,
+    W: Write,
+{
+    pub fn new(oracle: O, writer: W) -> Self {
+        Recorder { oracle, writer }
+    }
+
+    /// Returns the oracle and the writer.
+    pub fn into_inner(self) -> (O, W) {
+        (self.oracle, self.writer)
+    }
+}
+
+impl<O, W> Oracle for Recorder<O, W>
+where
+    O: Oracle,
+    O::Output: Recordable,
+    W: Write,
+{
+    type Output = O::Output;
+
+    fn query(&mut self, input: &[u8]) -> Result<O::Output, OracleError> {
+        let output = self.oracle.query(input);
+        let answer = match &output {
+            Ok(output) => bytes_to_hex(&output.to_record()),
+            Err(error) => format!("err {}", error.to_string().replace('\n', " ")),
+        };
+        writeln!(self.writer, "{} {}", bytes_to_hex(input), answer)?;
+        self.writer.flush()?;
+
+        output
+    }
+}
+
+/// An oracle answering from a recording made by `Recorder`. Queries have to
+/// come in the recorded order; the first one that differs is rejected, so a
+/// replay either reproduces the recorded run or says where it went another
+/// way. Recorded failures come back as `OracleError::Rejected` with the
+/// recorded message.
+///
+/// # Examples
+/// ```
+/// use set2::{
+///     oracle::{from_fn, Oracle},
+///     session::{Recorder, Replay},
+/// };
+///
+/// let mut recorder = Recorder::new(from_fn(|input: &[u8]| input.to_vec()), Vec::new());
+/// recorder.query(b"abc").unwrap();
+/// let (_, recording) = recorder.into_inner();
+///
+/// let mut replay = Replay::<Vec<u8>>::from_reader(&recording[..]).unwrap();
+/// assert_eq!(replay.query(b"abc").unwrap(), b"abc");
+/// assert!(replay.query(b"abc").is_err());
+/// ```
+pub struct Replay<T> {
+    entries: VecDeque<(Vec<u8>, Result<T, String>)>,
+    position: usize,
+}
+
+impl<T: Recordable> Replay<T> {
+    /// Reads a whole recording. Fails on lines `Recorder` would not write.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, OracleError> {
+        let malformed = |line: usize| {
+            OracleError::Rejected(format!("Malformed recording at line {}", line + 1))
+        };
+
+        let mut entries = VecDeque::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let (input, output) = line.split_once(' ').ok_or_else(|| malformed(index))?;
+            let input = hex_to_bytes(input).map_err(|_| malformed(index))?;
+            let output = match output.strip_prefix("err ") {
+                Some(message) => Err(message.to_string()),
+                None => hex_to_bytes(output)
+                    .ok()
+                    .and_then(|output| T::from_record(&output))
+                    .map(Ok)
+                    .ok_or_else(|| malformed(index))?,
+            };
+            entries.push_back((input, output));
+        }
+
+        Ok(Replay {
+            entries,
+            position: 0,
+        })
+    }
+
+    /// Returns how many recorded queries are left.
+    pub fn remaining(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl<T: Recordable> Oracle for Replay<T> {
+    type Output = T;
+
+    fn query(&mut self, input: &[u8]) -> Result<T, OracleError> {
+        let position = self.position;
+        match self.entries.pop_front() {
+            Some((recorded, output)) if recorded == input => {
+                self.position += 1;
+                output.map_err(OracleError::Rejected)
+            }
+            Some(entry) => {
+                self.entries.push_front(entry);
+                Err(OracleError::Rejected(format!(
+                    "Query {} differs from the recording",
+                    position
+                )))
+            }
+            None => Err(OracleError::Rejected(format!(
+                "The recording ends before query {}",
+                position
+            ))),
+        }
+    }
+}
+
+/// What a `Metered` oracle has seen so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub queries: usize,
+    /// Total length of the inputs.
+    pub bytes_sent: usize,
+    /// Time spent waiting for the oracle.
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    /// Returns the average number of queries per byte, for an attack that
+    /// recovered `recovered` bytes, or `None` if it recovered nothing.
+    pub fn queries_per_byte(&self, recovered: usize) -> Option<f64> {
+        (recovered > 0).then(|| self.queries as f64 / recovered as f64)
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} queries, {} bytes sent, {:?} in the oracle",
+            self.queries, self.bytes_sent, self.elapsed
+        )
+    }
+}
+
+/// An oracle that keeps `Stats` and, given a budget, refuses to make more
+/// queries than that with `OracleError::BudgetExhausted`.
+///
+/// # Examples
+/// ```
+/// use set2::{
+///     chal4::{byte_at_a_time, EcbSuffixOracle},
+///     session::Metered,
+/// };
+///
+/// let oracle = EcbSuffixOracle::new(&mut rand::thread_rng(), b"attack at dawn");
+/// let mut oracle = Metered::new(oracle);
+/// let report = byte_at_a_time(&mut oracle, |_| {}).unwrap();
+///
+/// assert_eq!(oracle.stats().queries, report.queries);
+/// if let Some(rate) = oracle.stats().queries_per_byte(report.suffix.len()) {
+///     println!("{:.1} queries per byte", rate);
+/// }
+/// ```
+pub struct Metered<O> {
+    oracle: O,
+    stats: Stats,
+    budget: Option<usize>,
+}
+
+impl<O: Oracle> Metered<O> {
+    pub fn new(oracle: O) -> Self {
+        Metered {
+            oracle,
+            stats: Stats::default(),
+            budget: None,
+        }
+    }
+
+    /// Allows at most `budget` queries in total, counting those already made.
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn into_inner(self) -> O {
+        self.oracle
+    }
+}
+
+impl<O: Oracle> Oracle for Metered<O> {
+    type Output = O::Output;
+
+    fn query(&mut self, input: &[u8]) -> Result<O::Output, OracleError> {
+        if let Some(budget) = self.budget {
+            if self.stats.queries >= budget {
+                return Err(OracleError::BudgetExhausted(budget));
+            }
+        }
+        self.stats.queries += 1;
+        self.stats.bytes_sent += input.len();
+
+        let start = Instant::now();
+        let output = self.oracle.query(input);
+        self.stats.elapsed += start.elapsed();
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal4::{byte_at_a_time, AttackError, EcbSuffixOracle};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{fs::File, io::BufReader};
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let oracle = EcbSuffixOracle::new(&mut rng, b"Rollin' in my 5.0");
+        let path = std::env::temp_dir().join(format!("set2-session-{}.txt", std::process::id()));
+
+        let mut recorder = Recorder::new(oracle, File::create(&path).unwrap());
+        let recorded = byte_at_a_time(&mut recorder, |_| {}).unwrap();
+        drop(recorder);
+
+        let mut replay = Replay::from_reader(BufReader::new(File::open(&path).unwrap())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.remaining(), recorded.queries);
+        let replayed = byte_at_a_time(&mut replay, |_| {}).unwrap();
+        assert_eq!(replayed, recorded);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn test_replay_divergence() {
+        let mut replay = Replay::<bool>::from_reader(&b"00 01\n01 00\n"[..]).unwrap();
+        assert!(replay.query(&[0]).unwrap());
+        assert!(matches!(replay.query(&[2]), Err(OracleError::Rejected(_))));
+        assert!(!replay.query(&[1]).unwrap());
+        assert!(matches!(replay.query(&[1]), Err(OracleError::Rejected(_))));
+
+        assert!(Replay::<bool>::from_reader(&b"00 02\n"[..]).is_err());
+        assert!(Replay::<Vec<u8>>::from_reader(&b"00\n"[..]).is_err());
+        assert!(Replay::<Vec<u8>>::from_reader(&b"zz 00\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_record_failures() {
+        let oracle = Failing(crate::oracle::from_fn(|input: &[u8]| input.to_vec()));
+        let mut recorder = Recorder::new(oracle, Vec::new());
+        assert_eq!(recorder.query(b"ok").unwrap(), b"ok");
+        assert!(recorder.query(b"").is_err());
+        let (_, recording) = recorder.into_inner();
+        assert_eq!(
+            String::from_utf8(recording.clone()).unwrap(),
+            "6f6b 6f6b\n err Oracle rejected the query: empty input\n"
+        );
+
+        let mut replay = Replay::<Vec<u8>>::from_reader(&recording[..]).unwrap();
+        assert_eq!(replay.query(b"ok").unwrap(), b"ok");
+        match replay.query(b"") {
+            Err(OracleError::Rejected(message)) => {
+                assert_eq!(message, "Oracle rejected the query: empty input")
+            }
+            other => panic!("expected the recorded failure, got {:?}", other),
+        }
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    /// Rejects empty inputs and passes the others on.
+    struct Failing<O>(O);
+
+    impl<O: Oracle> Oracle for Failing<O> {
+        type Output = O::Output;
+
+        fn query(&mut self, input: &[u8]) -> Result<O::Output, OracleError> {
+            if input.is_empty() {
+                return Err(OracleError::Rejected("empty input".to_string()));
+            }
+            self.0.query(input)
+        }
+    }
+
+    //------ metering ------//
+
+    #[test]
+    fn test_budget() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let oracle = EcbSuffixOracle::new(&mut rng, b"Rollin' in my 5.0");
+        let mut oracle = Metered::new(oracle).with_budget(100);
+
+        match byte_at_a_time(&mut oracle, |_| {}) {
+            Err(AttackError::Oracle(OracleError::BudgetExhausted(100))) => {}
+            other => panic!("expected the budget to run out, got {:?}", other),
+        }
+        assert_eq!(oracle.stats().queries, 100);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let suffix = b"Rollin' in my 5.0";
+        let oracle = EcbSuffixOracle::new(&mut rng, suffix);
+        let mut oracle = Metered::new(oracle);
+
+        let report = byte_at_a_time(&mut oracle, |_| {}).unwrap();
+        let stats = oracle.stats();
+        assert_eq!(stats.queries, report.queries);
+        assert!(stats.bytes_sent > 0);
+        assert_eq!(
+            stats.queries_per_byte(suffix.len()),
+            Some(report.queries as f64 / suffix.len() as f64)
+        );
+        assert_eq!(stats.queries_per_byte(0), None);
+    }
+}
@@ -0,0 +1,496 @@
+//! Oracles over the network
+//!
+//! Two transports, each with a client implementing `Oracle` and a server to
+//! put a target behind:
+//!
+//! - a line protocol over TCP: the client sends the input in hex on one line
+//!   and the server answers `ok <hex>` or `err <message>`;
+//! - HTTP/1.1 to a localhost server: the input is the body of a `POST` and
+//!   the answer is the status, the body and how long it took, for timing
+//!   leaks.
+//!
+//! Servers handle every connection on its own thread, so handlers take
+//! `&self` and keep any mutable state behind a lock.
+
+use set1::{chal1::hex_to_bytes, chal5::bytes_to_hex};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::oracle::{Oracle, OracleError};
+
+//------ line protocol ------//
+
+/// Longest line the line server reads, a megabyte of input in hex.
+const MAX_LINE_SIZE: usize = 2 * 1024 * 1024;
+
+/// A client for the TCP line protocol. One connection is kept open for all
+/// queries.
+///
+/// # Examples
+/// ```no_run
+/// use set2::{oracle::Oracle, transport::TcpOracle};
+///
+/// let mut oracle = TcpOracle::connect("127.0.0.1:9012").unwrap();
+/// let ciphertext = oracle.query(b"AAAA").unwrap();
+/// ```
+pub struct TcpOracle {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl TcpOracle {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let writer = TcpStream::connect(addr)?;
+        writer.set_nodelay(true)?;
+        let reader = BufReader::new(writer.try_clone()?);
+
+        Ok(TcpOracle { reader, writer })
+    }
+}
+
+impl Oracle for TcpOracle {
+    type Output = Vec<u8>;
+
+    fn query(&mut self, input: &[u8]) -> Result<Vec<u8>, OracleError> {
+        writeln!(self.writer, "{}", bytes_to_hex(input))?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        // an empty answer is `ok ` with its space trimmed.
+        let line = line.trim_end();
+        if line == "ok" {
+            Ok(Vec::new())
+        } else if let Some(hex) = line.strip_prefix("ok ") {
+            hex_to_bytes(hex).map_err(|error| OracleError::Rejected(error.to_string()))
+        } else if let Some(message) = line.strip_prefix("err ") {
+            Err(OracleError::Rejected(message.to_string()))
+        } else {
+            Err(OracleError::Rejected(format!(
+                "Malformed response {:?}",
+                line
+            )))
+        }
+    }
+}
+
+/// This function serves the line protocol on `listener` until it fails,
+/// answering every input with `handler`. Errors from the handler are sent
+/// back as `err` lines.
+pub fn serve_lines<H>(listener: TcpListener, handler: H) -> io::Result<()>
+where
+    H: Fn(&[u8]) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || handle_lines(stream, &*handler));
+    }
+
+    Ok(())
+}
+
+/// Answers the queries of one connection until the client hangs up. A line
+/// longer than `MAX_LINE_SIZE` gets an `err` answer and ends the connection.
+fn handle_lines<H>(stream: TcpStream, handler: &H) -> io::Result<()>
+where
+    H: Fn(&[u8]) -> Result<Vec<u8>, String>,
+{
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+
+    loop {
+        // one byte over the limit tells a long line from a full one.
+        line.clear();
+        let limit = MAX_LINE_SIZE as u64 + 1;
+        if (&mut reader).take(limit).read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        if line.len() > MAX_LINE_SIZE && !line.ends_with(b"\n") {
+            writeln!(writer, "err Line longer than {} bytes", MAX_LINE_SIZE)?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Line too long"));
+        }
+
+        let response = hex_to_bytes(String::from_utf8_lossy(&line).trim())
+            .map_err(|error| error.to_string())
+            .and_then(|input| handler(&input));
+
+        match response {
+            Ok(output) => writeln!(writer, "ok {}", bytes_to_hex(&output))?,
+            Err(message) => writeln!(writer, "err {}", message.replace('\n', " "))?,
+        }
+    }
+}
+
+//------ HTTP ------//
+
+/// Largest request head the HTTP server reads.
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// Largest request body the HTTP server accepts.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// What an HTTP oracle returns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    /// Time from sending the request to reading the whole response.
+    pub elapsed: Duration,
+}
+
+/// A request as the HTTP server hands it to its handler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+/// A client that `POST`s every input to one path of a localhost server, on a
+/// new connection each time.
+///
+/// # Examples
+/// ```no_run
+/// use set2::{oracle::Oracle, transport::HttpOracle};
+///
+/// let mut oracle = HttpOracle::new("127.0.0.1:9031".parse().unwrap(), "/test").unwrap();
+/// let response = oracle.query(b"file=foo&signature=00").unwrap();
+/// println!("{} after {:?}", response.status, response.elapsed);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpOracle {
+    addr: SocketAddr,
+    path: String,
+}
+
+impl HttpOracle {
+    /// Returns an error if `addr` is not a loopback address: the oracle is
+    /// meant for the bundled servers, not for anything on the network.
+    pub fn new(addr: SocketAddr, path: &str) -> Result<Self, &'static str> {
+        if !addr.ip().is_loopback() {
+            return Err("HTTP oracles only talk to localhost");
+        }
+        if !path.starts_with('/') || path.contains(char::is_whitespace) {
+            return Err("Path should start with '/' and hold no whitespace");
+        }
+
+        Ok(HttpOracle {
+            addr,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Oracle for HttpOracle {
+    type Output = HttpResponse;
+
+    fn query(&mut self, input: &[u8]) -> Result<HttpResponse, OracleError> {
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(self.addr)?;
+        stream.set_nodelay(true)?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.addr,
+            input.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(input);
+        stream.write_all(&request)?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let elapsed = start.elapsed();
+
+        let (status, body) = parse_response(&response)?;
+        Ok(HttpResponse {
+            status,
+            body,
+            elapsed,
+        })
+    }
+}
+
+/// Splits a response into its status code and body.
+fn parse_response(response: &[u8]) -> Result<(u16, Vec<u8>), OracleError> {
+    let malformed = || OracleError::Rejected("Malformed HTTP response".to_string());
+
+    let end = find_header_end(response).ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..end]).map_err(|_| malformed())?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(malformed)?;
+
+    Ok((status, response[end + 4..].to_vec()))
+}
+
+/// Returns where the blank line after the headers starts.
+fn find_header_end(data: &[u8]) -> Option<usize> {
+    data.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// This function serves HTTP/1.1 on `listener` until it fails. Every request
+/// is read whole, answered with the status and body from `handler` and the
+/// connection is closed.
+pub fn serve_http<H>(listener: TcpListener, handler: H) -> io::Result<()>
+where
+    H: Fn(&HttpRequest) -> (u16, Vec<u8>) + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || handle_http(stream, &*handler));
+    }
+
+    Ok(())
+}
+
+/// Reads one request, answers it and closes the connection. Requests with a
+/// head or body over the size limits get a 400.
+fn handle_http<H>(mut stream: TcpStream, handler: &H) -> io::Result<()>
+where
+    H: Fn(&HttpRequest) -> (u16, Vec<u8>),
+{
+    stream.set_nodelay(true)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP request");
+
+    // read up to the end of the headers, then the body they announce.
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let end = loop {
+        if let Some(end) = find_header_end(&data) {
+            break end;
+        }
+        if data.len() > MAX_HEADER_SIZE {
+            write_response(&mut stream, 400, b"Request head too large")?;
+            return Err(invalid());
+        }
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Err(invalid());
+        }
+        data.extend_from_slice(&buffer[..read]);
+    };
+
+    let head = std::str::from_utf8(&data[..end]).map_err(|_| invalid())?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(invalid()),
+    };
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>().map_err(|_| invalid()))
+        .transpose()?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        write_response(&mut stream, 400, b"Request body too large")?;
+        return Err(invalid());
+    }
+
+    let mut body = data[end + 4..].to_vec();
+    if body.len() < content_length {
+        let mut rest = vec![0u8; content_length - body.len()];
+        stream.read_exact(&mut rest)?;
+        body.extend(rest);
+    }
+    body.truncate(content_length);
+
+    let (status, response_body) = handler(&HttpRequest { method, path, body });
+    write_response(&mut stream, status, &response_body)
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &[u8]) -> io::Result<()> {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status),
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    stream.write_all(&response)
+}
+
+/// Returns the reason phrase for the status codes the servers use.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal4::{byte_at_a_time, EcbSuffixOracle};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::Mutex;
+
+    /// Binds a listener on a free localhost port.
+    fn listener() -> (TcpListener, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    //------ line protocol ------//
+
+    #[test]
+    fn test_line_protocol() {
+        let (listener, addr) = listener();
+        thread::spawn(move || {
+            serve_lines(listener, |input| match input {
+                b"fail" => Err("no\nthanks".to_string()),
+                _ => Ok(input.iter().rev().copied().collect()),
+            })
+        });
+
+        let mut oracle = TcpOracle::connect(addr).unwrap();
+        assert_eq!(oracle.query(b"abc").unwrap(), b"cba");
+        assert_eq!(oracle.query(b"").unwrap(), b"");
+        assert!(matches!(
+            oracle.query(b"fail"),
+            Err(OracleError::Rejected(message)) if message == "no thanks"
+        ));
+
+        // a mapped oracle, as a padding oracle client would use it.
+        let mut oracle = TcpOracle::connect(addr).unwrap().map(|bytes| bytes == [1]);
+        assert!(oracle.query(&[1]).unwrap());
+        assert!(!oracle.query(&[0]).unwrap());
+    }
+
+    #[test]
+    fn test_line_protocol_strict_ok() {
+        // a server that says `okay` has not answered.
+        let (listener, addr) = listener();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for _ in BufReader::new(stream).lines() {
+                writeln!(writer, "okay").unwrap();
+            }
+        });
+
+        let mut oracle = TcpOracle::connect(addr).unwrap();
+        assert!(matches!(oracle.query(b""), Err(OracleError::Rejected(_))));
+    }
+
+    #[test]
+    fn test_line_limit() {
+        let (listener, addr) = listener();
+        thread::spawn(move || serve_lines(listener, |input| Ok(input.to_vec())));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&vec![b'a'; MAX_LINE_SIZE + 1]).unwrap();
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).unwrap();
+        assert_eq!(
+            response,
+            format!("err Line longer than {} bytes\n", MAX_LINE_SIZE)
+        );
+
+        // the server is still up.
+        let mut oracle = TcpOracle::connect(addr).unwrap();
+        assert_eq!(oracle.query(b"still here").unwrap(), b"still here");
+    }
+
+    #[test]
+    fn test_byte_at_a_time_over_tcp() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let target = Mutex::new(EcbSuffixOracle::new(&mut rng, b"over the wire"));
+
+        let (listener, addr) = listener();
+        thread::spawn(move || {
+            serve_lines(listener, move |input| {
+                target
+                    .lock()
+                    .unwrap()
+                    .query(input)
+                    .map_err(|error| error.to_string())
+            })
+        });
+
+        let oracle = TcpOracle::connect(addr).unwrap();
+        let report = byte_at_a_time(oracle, |_| {}).unwrap();
+        assert_eq!(report.suffix, b"over the wire");
+    }
+
+    //------ HTTP ------//
+
+    #[test]
+    fn test_http() {
+        let (listener, addr) = listener();
+        thread::spawn(move || {
+            serve_http(listener, |request| match request.path.as_str() {
+                "/echo" if request.method == "POST" => (200, request.body.clone()),
+                _ => (404, b"not found".to_vec()),
+            })
+        });
+
+        let mut oracle = HttpOracle::new(addr, "/echo").unwrap();
+        let response = oracle.query(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"YELLOW SUBMARINE");
+
+        // larger than one read on the server.
+        let big = vec![0x42; 20_000];
+        assert_eq!(oracle.query(&big).unwrap().body, big);
+
+        let mut oracle = HttpOracle::new(addr, "/missing").unwrap();
+        assert_eq!(oracle.query(b"").unwrap().status, 404);
+
+        assert!(HttpOracle::new("10.0.0.1:80".parse().unwrap(), "/").is_err());
+        assert!(HttpOracle::new(addr, "no slash").is_err());
+    }
+
+    #[test]
+    fn test_http_limits() {
+        let (listener, addr) = listener();
+        thread::spawn(move || serve_http(listener, |request| (200, request.body.clone())));
+
+        let send = |request: &[u8]| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            // the server may close before taking all of it.
+            let _ = stream.write_all(request);
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response);
+            parse_response(&response).ok().map(|(status, _)| status)
+        };
+
+        assert_eq!(
+            send(b"POST / HTTP/1.1\r\nContent-Length: 99999999999999\r\n\r\n"),
+            Some(400)
+        );
+
+        // the server closes with part of the head unread, so the 400 can
+        // be lost to the reset that follows.
+        let mut long_head = b"POST / HTTP/1.1\r\nX: ".to_vec();
+        long_head.extend(vec![b'a'; 2 * MAX_HEADER_SIZE]);
+        assert!(matches!(send(&long_head), None | Some(400)));
+
+        // the server is still up.
+        let mut oracle = HttpOracle::new(addr, "/").unwrap();
+        assert_eq!(oracle.query(b"still here").unwrap().body, b"still here");
+    }
+}
@@ -0,0 +1,29 @@
+//! Serves the challenge 17 padding oracle over the TCP line protocol. It
+//! prints the IV and ciphertext of a challenge string in hex, then answers
+//! every `iv || ciphertext` with `01` for valid padding and `00` otherwise.
+//!
+//! Usage: padding_oracle_server [ADDR], listening on 127.0.0.1:9017 by default.
+
+use set1::{chal5::bytes_to_hex, padding::Pkcs7};
+use set2::transport::serve_lines;
+use set3::chal1::PaddingOracleService;
+use std::{env, net::TcpListener};
+
+fn main() -> std::io::Result<()> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:9017".to_string());
+    let mut rng = rand::thread_rng();
+    let service = PaddingOracleService::new(&mut rng, Pkcs7);
+
+    let (iv, ciphertext) = service.challenge_ciphertext(&mut rng);
+    println!("IV: {}", bytes_to_hex(&iv));
+    println!("Ciphertext: {}", bytes_to_hex(&ciphertext));
+
+    let listener = TcpListener::bind(&addr)?;
+    println!("Padding oracle listening on {}", listener.local_addr()?);
+
+    serve_lines(listener, move |input| {
+        Ok(vec![service.padding_valid(input) as u8])
+    })
+}
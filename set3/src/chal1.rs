@@ -0,0 +1,627 @@
+//! The CBC padding oracle
+//!
+//! The service decrypts any ciphertext we give it and only tells us whether
+//! the padding was valid. For a target block `C` and a forged previous block
+//! `C'`, the last plaintext byte is `D(C) ^ C'`, so finding the `C'` byte that
+//! makes it a valid one byte padding gives that byte of `D(C)`. Setting the
+//! bytes already found to the next padding length moves on to the byte
+//! before, and `D(C) ^ previous block` is the plaintext. The IV is just the
+//! block before the first one, so every block comes out.
+
+use rand::Rng;
+use set1::{
+    aes::{Aes, BLOCK_SIZE},
+    chal6::base64_to_bytes,
+    modes::{BlockMode, Cbc},
+    padding::{Padding, Pkcs7},
+};
+use set2::{
+    chal3::{random_aes_key, random_bytes},
+    concurrent::Concurrent,
+    oracle::{Oracle, OracleError},
+};
+use std::fmt;
+
+/// The strings the challenge service picks from.
+const STRINGS: [&str; 10] = [
+    "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+    "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+    "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+    "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+    "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+    "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+    "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+    "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+    "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+    "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+];
+
+/// Encrypts with AES-128-CBC under a key chosen at construction, and tells
+/// whether ciphertexts decrypt to valid padding of scheme `P`.
+#[derive(Clone)]
+pub struct PaddingOracleService<P> {
+    key: [u8; BLOCK_SIZE],
+    padding: P,
+}
+
+impl<P: Padding> PaddingOracleService<P> {
+    /// Creates the service with a random key.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, padding: P) -> Self {
+        PaddingOracleService {
+            key: random_aes_key(rng),
+            padding,
+        }
+    }
+
+    /// Pads and encrypts `plaintext` under a random IV, returned first.
+    pub fn encrypt<R: Rng + ?Sized>(&self, rng: &mut R, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let iv = random_bytes(rng, BLOCK_SIZE);
+        let padded = self.padding.pad(plaintext, BLOCK_SIZE).unwrap();
+        let mut cbc = Cbc::new(Aes::new(&self.key).unwrap(), &iv).unwrap();
+
+        let ciphertext = cbc.encrypt(&padded).unwrap();
+        (iv, ciphertext)
+    }
+
+    /// Encrypts one of the challenge strings, picked at random.
+    pub fn challenge_ciphertext<R: Rng + ?Sized>(&self, rng: &mut R) -> (Vec<u8>, Vec<u8>) {
+        let string = STRINGS[rng.gen_range(0..STRINGS.len())];
+        self.encrypt(rng, &base64_to_bytes(string).unwrap())
+    }
+
+    /// Decrypts `iv || ciphertext` and returns whether the padding is valid.
+    pub fn padding_valid(&self, iv_and_ciphertext: &[u8]) -> bool {
+        if iv_and_ciphertext.len() < 2 * BLOCK_SIZE {
+            return false;
+        }
+
+        let (iv, ciphertext) = iv_and_ciphertext.split_at(BLOCK_SIZE);
+        let mut cbc = Cbc::new(Aes::new(&self.key).unwrap(), iv).unwrap();
+        match cbc.decrypt(ciphertext) {
+            Ok(padded) => self.padding.unpad(&padded, BLOCK_SIZE).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+impl<P: Padding> Oracle for PaddingOracleService<P> {
+    type Output = bool;
+
+    /// Takes `iv || ciphertext` and answers whether the padding is valid.
+    fn query(&mut self, input: &[u8]) -> Result<bool, OracleError> {
+        Ok(self.padding_valid(input))
+    }
+}
+
+/// A padding oracle that lies: every answer is flipped with probability
+/// `error_rate`, like a timing oracle reading a noisy clock.
+pub struct NoisyOracle<O, R> {
+    oracle: O,
+    rng: R,
+    error_rate: f64,
+}
+
+impl<O: Oracle<Output = bool>, R: Rng> NoisyOracle<O, R> {
+    pub fn new(oracle: O, rng: R, error_rate: f64) -> Self {
+        NoisyOracle {
+            oracle,
+            rng,
+            error_rate,
+        }
+    }
+}
+
+impl<O: Oracle<Output = bool>, R: Rng> Oracle for NoisyOracle<O, R> {
+    type Output = bool;
+
+    fn query(&mut self, input: &[u8]) -> Result<bool, OracleError> {
+        let answer = self.oracle.query(input)?;
+        Ok(answer ^ self.rng.gen_bool(self.error_rate))
+    }
+}
+
+/// How hard to try against an unreliable oracle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttackConfig {
+    pub block_size: usize,
+    /// Queries behind every `valid` answer that is acted on; the majority
+    /// wins. One trusts the oracle.
+    pub votes: usize,
+    /// Passes over the 256 values of a byte before giving up, for oracles
+    /// that sometimes say `invalid` to the right one.
+    pub max_sweeps: usize,
+}
+
+impl Default for AttackConfig {
+    /// An AES block size and a reliable oracle.
+    fn default() -> Self {
+        AttackConfig {
+            block_size: BLOCK_SIZE,
+            votes: 1,
+            max_sweeps: 1,
+        }
+    }
+}
+
+/// Why the attack stopped.
+#[derive(Debug)]
+pub enum PaddingOracleError {
+    /// The oracle failed to answer.
+    Oracle(OracleError),
+    /// The ciphertext or IV has the wrong length, or the recovered plaintext
+    /// doesn't unpad.
+    InvalidInput(&'static str),
+    /// The padding scheme doesn't check the byte at this position of a
+    /// block, so the oracle says nothing about it. ISO 10126 random bytes
+    /// and zero padding are like this.
+    UnsupportedPadding(usize),
+    /// No value was accepted for this byte of this block.
+    ByteNotFound { block: usize, position: usize },
+}
+
+impl fmt::Display for PaddingOracleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaddingOracleError::Oracle(error) => write!(f, "{}", error),
+            PaddingOracleError::InvalidInput(error) => write!(f, "{}", error),
+            PaddingOracleError::UnsupportedPadding(position) => write!(
+                f,
+                "The padding doesn't check byte {} of the block, the oracle can't recover it",
+                position
+            ),
+            PaddingOracleError::ByteNotFound { block, position } => write!(
+                f,
+                "No value gave valid padding for byte {} of block {}",
+                position, block
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PaddingOracleError {}
+
+impl From<OracleError> for PaddingOracleError {
+    fn from(error: OracleError) -> Self {
+        PaddingOracleError::Oracle(error)
+    }
+}
+
+/// Returns the padding bytes of scheme `padding` that fill a block from
+/// `position` on, and checks that changing the first of them makes the
+/// padding invalid, since otherwise the oracle tells nothing about the byte
+/// at `position`.
+fn padding_pattern(
+    padding: &dyn Padding,
+    block_size: usize,
+    position: usize,
+) -> Result<Vec<u8>, PaddingOracleError> {
+    let padded = padding
+        .pad(&vec![0; position], block_size)
+        .map_err(PaddingOracleError::InvalidInput)?;
+    if padded.len() != block_size {
+        return Err(PaddingOracleError::UnsupportedPadding(position));
+    }
+
+    let mut changed = padded.clone();
+    changed[position] ^= 1;
+    if padding.unpad(&changed, block_size).is_ok() {
+        return Err(PaddingOracleError::UnsupportedPadding(position));
+    }
+
+    Ok(padded[position..].to_vec())
+}
+
+/// An oracle that can take the first queries of a byte's guesses a window at
+/// a time.
+trait Guesses: Oracle<Output = bool> {
+    fn window(&self) -> usize;
+
+    /// Answers `inputs` in order.
+    fn query_window(&mut self, inputs: &[Vec<u8>]) -> Result<Vec<bool>, OracleError>;
+}
+
+/// One guess at a time, so no query is made past the right one.
+struct Sequential<O>(O);
+
+impl<O: Oracle<Output = bool>> Oracle for Sequential<O> {
+    type Output = bool;
+
+    fn query(&mut self, input: &[u8]) -> Result<bool, OracleError> {
+        self.0.query(input)
+    }
+}
+
+impl<O: Oracle<Output = bool>> Guesses for Sequential<O> {
+    fn window(&self) -> usize {
+        1
+    }
+
+    fn query_window(&mut self, inputs: &[Vec<u8>]) -> Result<Vec<bool>, OracleError> {
+        inputs.iter().map(|input| self.0.query(input)).collect()
+    }
+}
+
+impl<O: Oracle<Output = bool> + Clone + Send> Guesses for Concurrent<O> {
+    fn window(&self) -> usize {
+        self.parallelism()
+    }
+
+    fn query_window(&mut self, inputs: &[Vec<u8>]) -> Result<Vec<bool>, OracleError> {
+        self.query_all(inputs)
+    }
+}
+
+/// Sends `forged || block` `votes` times and returns whether most answers
+/// were `valid`.
+fn vote<O: Oracle<Output = bool>>(
+    oracle: &mut O,
+    forged: &[u8],
+    block: &[u8],
+    votes: usize,
+) -> Result<bool, OracleError> {
+    let mut input = forged.to_vec();
+    input.extend_from_slice(block);
+
+    let mut valid = 0;
+    for _ in 0..votes {
+        if oracle.query(&input)? {
+            valid += 1;
+        }
+    }
+    Ok(2 * valid > votes)
+}
+
+/// Recovers `D(block)`, the block decrypted but not yet XORed with the one
+/// before it, from right to left. A `valid` answer is trusted only if the
+/// vote agrees, and if it still holds after changing the byte to the left:
+/// otherwise the padding was longer than intended, which happens when the
+/// bytes to the left happen to look like padding, e.g. `02 02` instead of
+/// `01` on the last byte. The first query for each guess goes out a window
+/// at a time, and the answers are read in order, so the guess taken does not
+/// depend on the window.
+fn decrypt_block<G: Guesses>(
+    oracle: &mut G,
+    block: &[u8],
+    index: usize,
+    padding: &dyn Padding,
+    config: &AttackConfig,
+) -> Result<Vec<u8>, PaddingOracleError> {
+    let block_size = config.block_size;
+    let mut intermediate = vec![0u8; block_size];
+    let guesses: Vec<u8> = (0..=255).collect();
+
+    for position in (0..block_size).rev() {
+        let pattern = padding_pattern(padding, block_size, position)?;
+
+        let mut forged = vec![0u8; block_size];
+        for i in position + 1..block_size {
+            forged[i] = intermediate[i] ^ pattern[i - position];
+        }
+
+        let mut found = None;
+        'sweeps: for _ in 0..config.max_sweeps {
+            for window in guesses.chunks(oracle.window()) {
+                let inputs: Vec<Vec<u8>> = window
+                    .iter()
+                    .map(|&guess| {
+                        forged[position] = guess;
+                        [&forged, block].concat()
+                    })
+                    .collect();
+                let answers = oracle.query_window(&inputs)?;
+
+                for (&guess, valid) in window.iter().zip(answers) {
+                    if !valid {
+                        continue;
+                    }
+                    forged[position] = guess;
+                    if config.votes > 1 && !vote(oracle, &forged, block, config.votes)? {
+                        continue;
+                    }
+                    if position > 0 {
+                        let mut changed = forged.clone();
+                        changed[position - 1] ^= 1;
+                        if !vote(oracle, &changed, block, config.votes)? {
+                            continue;
+                        }
+                    }
+
+                    found = Some(guess);
+                    break 'sweeps;
+                }
+            }
+        }
+
+        let guess = found.ok_or(PaddingOracleError::ByteNotFound {
+            block: index,
+            position,
+        })?;
+        intermediate[position] = guess ^ pattern[0];
+    }
+
+    Ok(intermediate)
+}
+
+/// This function decrypts `ciphertext` with nothing but a padding oracle. The
+/// oracle takes `iv || ciphertext` and says whether it decrypts to valid
+/// padding of scheme `padding`; queries are always two blocks, a forged IV
+/// and the block being decrypted. Schemes that check every padding byte
+/// (PKCS#7, ANSI X.923, ISO/IEC 7816-4) work; the returned plaintext is
+/// unpadded.
+///
+/// # Examples
+/// ```
+/// use set1::padding::Pkcs7;
+/// use set3::chal1::{padding_oracle_attack, AttackConfig, PaddingOracleService};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+/// let (iv, ciphertext) = service.encrypt(&mut rng, b"Cooking MC's like a pound of bacon");
+///
+/// let plaintext =
+///     padding_oracle_attack(&mut service, &iv, &ciphertext, &Pkcs7, &AttackConfig::default())
+///         .unwrap();
+/// assert_eq!(plaintext, b"Cooking MC's like a pound of bacon");
+/// ```
+pub fn padding_oracle_attack<O: Oracle<Output = bool>>(
+    oracle: O,
+    iv: &[u8],
+    ciphertext: &[u8],
+    padding: &dyn Padding,
+    config: &AttackConfig,
+) -> Result<Vec<u8>, PaddingOracleError> {
+    attack(&mut Sequential(oracle), iv, ciphertext, padding, config)
+}
+
+/// This function is `padding_oracle_attack` with up to `parallelism` queries
+/// in flight at once, each thread on its own clone of `oracle`, for oracles
+/// that are slow to answer, like a server over HTTP. Against an oracle that
+/// always answers the same it returns what `padding_oracle_attack` does; it
+/// may make up to `parallelism - 1` extra queries per byte.
+///
+/// # Examples
+/// ```
+/// use set1::padding::Pkcs7;
+/// use set3::chal1::{padding_oracle_attack_concurrent, AttackConfig, PaddingOracleService};
+///
+/// let mut rng = rand::thread_rng();
+/// let service = PaddingOracleService::new(&mut rng, Pkcs7);
+/// let (iv, ciphertext) = service.encrypt(&mut rng, b"Cooking MC's like a pound of bacon");
+///
+/// let config = AttackConfig::default();
+/// let plaintext =
+///     padding_oracle_attack_concurrent(service, &iv, &ciphertext, &Pkcs7, &config, 8).unwrap();
+/// assert_eq!(plaintext, b"Cooking MC's like a pound of bacon");
+/// ```
+pub fn padding_oracle_attack_concurrent<O>(
+    oracle: O,
+    iv: &[u8],
+    ciphertext: &[u8],
+    padding: &dyn Padding,
+    config: &AttackConfig,
+    parallelism: usize,
+) -> Result<Vec<u8>, PaddingOracleError>
+where
+    O: Oracle<Output = bool> + Clone + Send,
+{
+    let mut oracle = Concurrent::new(oracle, parallelism);
+    attack(&mut oracle, iv, ciphertext, padding, config)
+}
+
+fn attack<G: Guesses>(
+    oracle: &mut G,
+    iv: &[u8],
+    ciphertext: &[u8],
+    padding: &dyn Padding,
+    config: &AttackConfig,
+) -> Result<Vec<u8>, PaddingOracleError> {
+    let block_size = config.block_size;
+    if block_size == 0 || iv.len() != block_size {
+        return Err(PaddingOracleError::InvalidInput(
+            "IV length should be equal to the block size",
+        ));
+    }
+    if ciphertext.is_empty() || ciphertext.len() % block_size != 0 {
+        return Err(PaddingOracleError::InvalidInput(
+            "Ciphertext length should be a non-zero multiple of the block size",
+        ));
+    }
+
+    let mut padded = Vec::with_capacity(ciphertext.len());
+    let mut previous = iv;
+    for (index, block) in ciphertext.chunks(block_size).enumerate() {
+        let intermediate = decrypt_block(oracle, block, index, padding, config)?;
+        padded.extend(intermediate.iter().zip(previous).map(|(a, b)| a ^ b));
+        previous = block;
+    }
+
+    padding
+        .unpad(&padded, block_size)
+        .map_err(PaddingOracleError::InvalidInput)
+}
+
+/// This function decrypts a challenge ciphertext through the padding oracle
+/// and prints it.
+pub fn cbc_padding_oracle() {
+    let mut rng = rand::thread_rng();
+    let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+    let (iv, ciphertext) = service.challenge_ciphertext(&mut rng);
+
+    let plaintext = padding_oracle_attack(
+        &mut service,
+        &iv,
+        &ciphertext,
+        &Pkcs7,
+        &AttackConfig::default(),
+    )
+    .unwrap();
+    println!("Decryption: {}", String::from_utf8_lossy(&plaintext));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use set1::padding::{AnsiX923, Iso10126, Iso7816, ZeroPadding};
+    use set2::transport::{serve_http, HttpOracle, HttpResponse};
+    use std::{net::TcpListener, thread};
+
+    //------ reliable oracle ------//
+
+    #[test]
+    fn test_challenge_strings() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+        let strings: Vec<Vec<u8>> = STRINGS
+            .iter()
+            .map(|s| base64_to_bytes(s).unwrap())
+            .collect();
+
+        for _ in 0..5 {
+            let (iv, ciphertext) = service.challenge_ciphertext(&mut rng);
+            let plaintext = padding_oracle_attack(
+                &mut service,
+                &iv,
+                &ciphertext,
+                &Pkcs7,
+                &AttackConfig::default(),
+            )
+            .unwrap();
+            assert!(strings.contains(&plaintext));
+        }
+    }
+
+    #[test]
+    fn test_last_byte_false_positive() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+
+        // plaintexts ending with `02 02` make the zeroed forged byte before
+        // the last one give valid two byte padding as well.
+        for _ in 0..10 {
+            let (iv, ciphertext) = service.encrypt(&mut rng, b"YELLOW SUBMARINE\x02\x02");
+            let plaintext = padding_oracle_attack(
+                &mut service,
+                &iv,
+                &ciphertext,
+                &Pkcs7,
+                &AttackConfig::default(),
+            )
+            .unwrap();
+            assert_eq!(plaintext, b"YELLOW SUBMARINE\x02\x02");
+        }
+    }
+
+    #[test]
+    fn test_padding_schemes() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let message = b"With the bass kicked in and the Vega's are pumpin'";
+
+        let mut service = PaddingOracleService::new(&mut rng, AnsiX923);
+        let (iv, ciphertext) = service.encrypt(&mut rng, message);
+        let config = AttackConfig::default();
+        let plaintext = padding_oracle_attack(&mut service, &iv, &ciphertext, &AnsiX923, &config);
+        assert_eq!(plaintext.unwrap(), message);
+
+        let mut service = PaddingOracleService::new(&mut rng, Iso7816);
+        let (iv, ciphertext) = service.encrypt(&mut rng, message);
+        let plaintext = padding_oracle_attack(&mut service, &iv, &ciphertext, &Iso7816, &config);
+        assert_eq!(plaintext.unwrap(), message);
+
+        // random or unchecked padding bytes leave nothing to find.
+        let mut service = PaddingOracleService::new(&mut rng, Iso10126);
+        let (iv, ciphertext) = service.encrypt(&mut rng, message);
+        assert!(matches!(
+            padding_oracle_attack(&mut service, &iv, &ciphertext, &Iso10126, &config),
+            Err(PaddingOracleError::UnsupportedPadding(14))
+        ));
+        let mut service = PaddingOracleService::new(&mut rng, ZeroPadding);
+        let (iv, ciphertext) = service.encrypt(&mut rng, message);
+        assert!(matches!(
+            padding_oracle_attack(&mut service, &iv, &ciphertext, &ZeroPadding, &config),
+            Err(PaddingOracleError::UnsupportedPadding(_))
+        ));
+    }
+
+    #[test]
+    fn test_attack_errors() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+        let config = AttackConfig::default();
+
+        assert!(padding_oracle_attack(&mut service, &[0; 8], &[0; 16], &Pkcs7, &config).is_err());
+        assert!(padding_oracle_attack(&mut service, &[0; 16], &[0; 20], &Pkcs7, &config).is_err());
+        assert!(padding_oracle_attack(&mut service, &[0; 16], &[], &Pkcs7, &config).is_err());
+    }
+
+    //------ noisy oracle ------//
+
+    #[test]
+    fn test_noisy_oracle() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let service = PaddingOracleService::new(&mut rng, Pkcs7);
+        let (iv, ciphertext) = service.encrypt(&mut rng, b"Quick to the point, to the point");
+
+        let mut noisy = NoisyOracle::new(service, StdRng::seed_from_u64(170), 0.05);
+        let config = AttackConfig {
+            votes: 7,
+            max_sweeps: 8,
+            ..AttackConfig::default()
+        };
+        let plaintext =
+            padding_oracle_attack(&mut noisy, &iv, &ciphertext, &Pkcs7, &config).unwrap();
+        assert_eq!(plaintext, b"Quick to the point, to the point");
+    }
+
+    //------ concurrent queries ------//
+
+    #[test]
+    fn test_concurrent_same_as_sequential() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut service = PaddingOracleService::new(&mut rng, Pkcs7);
+        let config = AttackConfig::default();
+
+        let mut ciphertexts = vec![service.encrypt(&mut rng, b"YELLOW SUBMARINE\x02\x02")];
+        ciphertexts.push(service.challenge_ciphertext(&mut rng));
+        for (iv, ciphertext) in ciphertexts {
+            let expected =
+                padding_oracle_attack(&mut service, &iv, &ciphertext, &Pkcs7, &config).unwrap();
+            for parallelism in [1, 16, 256] {
+                let plaintext = padding_oracle_attack_concurrent(
+                    service.clone(),
+                    &iv,
+                    &ciphertext,
+                    &Pkcs7,
+                    &config,
+                    parallelism,
+                )
+                .unwrap();
+                assert_eq!(plaintext, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_concurrent_over_http() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let service = PaddingOracleService::new(&mut rng, Pkcs7);
+        let (iv, ciphertext) = service.encrypt(&mut rng, b"Vanilla Ice");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve_http(listener, move |request| {
+                (200, vec![service.padding_valid(&request.body) as u8])
+            })
+        });
+
+        let oracle = HttpOracle::new(addr, "/")
+            .unwrap()
+            .map(|response: HttpResponse| response.body == [1]);
+        let config = AttackConfig::default();
+        let plaintext =
+            padding_oracle_attack_concurrent(oracle, &iv, &ciphertext, &Pkcs7, &config, 16)
+                .unwrap();
+        assert_eq!(plaintext, b"Vanilla Ice");
+    }
+}
@@ -0,0 +1,390 @@
+//! The MT19937 Mersenne Twister RNG
+//!
+//! Both the 32-bit generator and its 64-bit variant, MT19937-64, seeded like
+//! the reference implementations (`init_genrand` and `init_by_array`), so
+//! their outputs match them exactly. They implement `RngCore`, which makes
+//! them a drop-in `R: Rng` for the oracles that draw random keys, and an
+//! oracle seeded with a known value draws known keys.
+
+use rand::{Error, RngCore};
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908b0df;
+const UPPER_MASK: u32 = 0x80000000;
+const LOWER_MASK: u32 = 0x7fffffff;
+
+/// The 32-bit Mersenne Twister.
+///
+/// # Examples
+/// ```
+/// use rand::RngCore;
+/// use set3::chal5::Mt19937;
+///
+/// let mut rng = Mt19937::new(5489);
+/// assert_eq!(rng.next_u32(), 3499211612);
+/// ```
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; N],
+    /// Index of the next state word to temper, `N` once all are used.
+    index: usize,
+}
+
+impl Mt19937 {
+    /// Creates the generator seeded with `init_genrand(seed)`.
+    pub fn new(seed: u32) -> Self {
+        let mut rng = Mt19937 {
+            state: [0; N],
+            index: N,
+        };
+        rng.init_genrand(seed);
+        rng
+    }
+
+    /// Creates the generator seeded with `init_by_array(key)`.
+    pub fn from_key(key: &[u32]) -> Self {
+        let mut rng = Mt19937::new(0);
+        rng.init_by_array(key);
+        rng
+    }
+
+    /// Reseeds from a single word.
+    pub fn init_genrand(&mut self, seed: u32) {
+        self.state[0] = seed;
+        for i in 1..N {
+            let previous = self.state[i - 1];
+            self.state[i] = 1812433253u32
+                .wrapping_mul(previous ^ (previous >> 30))
+                .wrapping_add(i as u32);
+        }
+        self.index = N;
+    }
+
+    /// Reseeds from an array of words of any length, empty included.
+    pub fn init_by_array(&mut self, key: &[u32]) {
+        self.init_genrand(19650218);
+
+        let mut i = 1;
+        let mut j = 0;
+        for _ in 0..N.max(key.len()) {
+            let previous = self.state[i - 1];
+            self.state[i] = (self.state[i] ^ (previous ^ (previous >> 30)).wrapping_mul(1664525))
+                .wrapping_add(key.get(j).copied().unwrap_or(0))
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                self.state[0] = self.state[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            let previous = self.state[i - 1];
+            self.state[i] = (self.state[i]
+                ^ (previous ^ (previous >> 30)).wrapping_mul(1566083941))
+            .wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                self.state[0] = self.state[N - 1];
+                i = 1;
+            }
+        }
+
+        // the most significant bit is 1, so the state is never all zeroes.
+        self.state[0] = 0x80000000;
+        self.index = N;
+    }
+
+    /// Generates the next `N` state words.
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 1 { MATRIX_A } else { 0 };
+            self.state[i] = self.state[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937 {
+    /// The reference `genrand_int32`.
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c5680;
+        y ^= (y << 15) & 0xefc60000;
+        y ^ (y >> 18)
+    }
+
+    /// Two outputs, the first one low.
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        (self.next_u32() as u64) << 32 | low
+    }
+
+    /// Outputs in little-endian order; the last one may be cut short.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+const NN: usize = 312;
+const MM: usize = 156;
+const MATRIX_A_64: u64 = 0xb5026f5aa96619e9;
+const UPPER_MASK_64: u64 = 0xffffffff80000000;
+const LOWER_MASK_64: u64 = 0x7fffffff;
+
+/// The 64-bit Mersenne Twister.
+///
+/// # Examples
+/// ```
+/// use rand::RngCore;
+/// use set3::chal5::Mt19937_64;
+///
+/// let mut rng = Mt19937_64::new(5489);
+/// assert_eq!(rng.next_u64(), 14514284786278117030);
+/// ```
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; NN],
+    /// Index of the next state word to temper, `NN` once all are used.
+    index: usize,
+}
+
+impl Mt19937_64 {
+    /// Creates the generator seeded with `init_genrand64(seed)`.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Mt19937_64 {
+            state: [0; NN],
+            index: NN,
+        };
+        rng.init_genrand(seed);
+        rng
+    }
+
+    /// Creates the generator seeded with `init_by_array64(key)`.
+    pub fn from_key(key: &[u64]) -> Self {
+        let mut rng = Mt19937_64::new(0);
+        rng.init_by_array(key);
+        rng
+    }
+
+    /// Reseeds from a single word.
+    pub fn init_genrand(&mut self, seed: u64) {
+        self.state[0] = seed;
+        for i in 1..NN {
+            let previous = self.state[i - 1];
+            self.state[i] = 6364136223846793005u64
+                .wrapping_mul(previous ^ (previous >> 62))
+                .wrapping_add(i as u64);
+        }
+        self.index = NN;
+    }
+
+    /// Reseeds from an array of words of any length, empty included.
+    pub fn init_by_array(&mut self, key: &[u64]) {
+        self.init_genrand(19650218);
+
+        let mut i = 1;
+        let mut j = 0;
+        for _ in 0..NN.max(key.len()) {
+            let previous = self.state[i - 1];
+            self.state[i] = (self.state[i]
+                ^ (previous ^ (previous >> 62)).wrapping_mul(3935559000370003845))
+            .wrapping_add(key.get(j).copied().unwrap_or(0))
+            .wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= NN {
+                self.state[0] = self.state[NN - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..NN - 1 {
+            let previous = self.state[i - 1];
+            self.state[i] = (self.state[i]
+                ^ (previous ^ (previous >> 62)).wrapping_mul(2862933555777941757))
+            .wrapping_sub(i as u64);
+            i += 1;
+            if i >= NN {
+                self.state[0] = self.state[NN - 1];
+                i = 1;
+            }
+        }
+
+        self.state[0] = 1 << 63;
+        self.index = NN;
+    }
+
+    /// Generates the next `NN` state words.
+    fn twist(&mut self) {
+        for i in 0..NN {
+            let x = (self.state[i] & UPPER_MASK_64) | (self.state[(i + 1) % NN] & LOWER_MASK_64);
+            let mag = if x & 1 == 1 { MATRIX_A_64 } else { 0 };
+            self.state[i] = self.state[(i + MM) % NN] ^ (x >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    /// The upper half of the next 64-bit output.
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// The reference `genrand64_int64`.
+    fn next_u64(&mut self) -> u64 {
+        if self.index >= NN {
+            self.twist();
+        }
+        let mut x = self.state[self.index];
+        self.index += 1;
+
+        x ^= (x >> 29) & 0x5555555555555555;
+        x ^= (x << 17) & 0x71d67fffeda60000;
+        x ^= (x << 37) & 0xfff7eee000000000;
+        x ^ (x >> 43)
+    }
+
+    /// Outputs in little-endian order; the last one may be cut short.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// This function prints the first outputs of both generators for the
+/// default seed.
+pub fn implement_mt19937() {
+    let mut rng = Mt19937::new(5489);
+    let outputs: Vec<u32> = (0..5).map(|_| rng.next_u32()).collect();
+    println!("MT19937: {:?}", outputs);
+
+    let mut rng = Mt19937_64::new(5489);
+    let outputs: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+    println!("MT19937-64: {:?}", outputs);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chal1::PaddingOracleService;
+    use rand::Rng;
+    use set1::padding::Pkcs7;
+    use set2::chal4::{byte_at_a_time, EcbSuffixOracle};
+
+    //------ reference outputs ------//
+
+    #[test]
+    fn test_mt19937_init_genrand() {
+        let mut rng = Mt19937::new(5489);
+        let outputs: Vec<u32> = (0..5).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            [3499211612, 581869302, 3890346734, 3586334585, 545404204]
+        );
+
+        // the 10000th output, as required of `std::mt19937` in C++.
+        let mut rng = Mt19937::new(5489);
+        let output = (0..10000).map(|_| rng.next_u32()).last().unwrap();
+        assert_eq!(output, 4123659995);
+    }
+
+    #[test]
+    fn test_mt19937_init_by_array() {
+        // the start of mt19937ar.out.
+        let mut rng = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        let outputs: Vec<u32> = (0..5).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            [1067595299, 955945823, 477289528, 4107218783, 4228976476]
+        );
+    }
+
+    #[test]
+    fn test_mt19937_64_init_genrand() {
+        // the 10000th output, as required of `std::mt19937_64` in C++.
+        let mut rng = Mt19937_64::new(5489);
+        let output = (0..10000).map(|_| rng.next_u64()).last().unwrap();
+        assert_eq!(output, 9981545732273789042);
+    }
+
+    #[test]
+    fn test_mt19937_64_init_by_array() {
+        // the start of mt19937-64.out.
+        let mut rng = Mt19937_64::from_key(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let outputs: Vec<u64> = (0..5).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            outputs,
+            [
+                7266447313870364031,
+                4946485549665804864,
+                16945909448695747420,
+                16394063075524226720,
+                4873882236456199058
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reseed() {
+        let mut rng = Mt19937::new(1);
+        rng.next_u32();
+        rng.init_genrand(5489);
+        assert_eq!(rng.next_u32(), 3499211612);
+
+        let mut rng = Mt19937_64::new(1);
+        rng.next_u64();
+        rng.init_by_array(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        assert_eq!(rng.next_u64(), 7266447313870364031);
+    }
+
+    //------ driving the oracles ------//
+
+    #[test]
+    fn test_drives_oracles() {
+        let mut oracle = EcbSuffixOracle::new(&mut Mt19937::new(21), b"Vanilla Ice");
+        assert_eq!(
+            byte_at_a_time(&mut oracle, |_| {}).unwrap().suffix,
+            b"Vanilla Ice"
+        );
+
+        // the same seed draws the same key and IV.
+        let encrypt = |seed| {
+            let mut rng = Mt19937_64::new(seed);
+            let service = PaddingOracleService::new(&mut rng, Pkcs7);
+            service.encrypt(&mut rng, b"Vanilla Ice")
+        };
+        assert_eq!(encrypt(21), encrypt(21));
+        assert_ne!(encrypt(21), encrypt(22));
+
+        let mut rng = Mt19937::new(21);
+        assert!((0..100).all(|_| rng.gen_range(5..10) >= 5));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod chal1;
+pub mod chal5;
@@ -0,0 +1,6 @@
+use set3::{chal1::cbc_padding_oracle, chal5::implement_mt19937};
+
+fn main() {
+    cbc_padding_oracle();
+    implement_mt19937();
+}
@@ -0,0 +1,24 @@
+//! Serves the challenge 31 HMAC-SHA1 check with a timing leak over HTTP.
+//! `POST /test` with `file=...&signature=...` answers 200 for a valid
+//! signature and 500 otherwise, taking longer the more leading bytes match.
+//!
+//! Usage: hmac_timing_server [ADDR] [DELAY_MS], listening on 127.0.0.1:9031
+//! with a 50 ms delay by default.
+
+use set4::chal7::HmacServer;
+use std::{env, net::TcpListener, time::Duration};
+
+fn main() -> std::io::Result<()> {
+    let mut args = env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:9031".to_string());
+    let delay = args
+        .next()
+        .map(|delay| delay.parse().expect("DELAY_MS should be a number"))
+        .unwrap_or(50);
+
+    let server = HmacServer::new(&mut rand::thread_rng(), Duration::from_millis(delay));
+    let listener = TcpListener::bind(&addr)?;
+    println!("HMAC timing leak listening on {}", listener.local_addr()?);
+
+    server.serve(listener)
+}
@@ -0,0 +1,142 @@
+//! Break "random access read/write" AES CTR
+//!
+//! The service lets anyone rewrite part of a ciphertext: it decrypts nothing,
+//! it only encrypts the new bytes under the keystream of their offset. Asking
+//! it to write zeroes over the whole ciphertext hands back the keystream
+//! itself, and XORing that with the ciphertext gives the plaintext.
+
+use rand::Rng;
+use set1::{
+    aes::{Aes, BLOCK_SIZE},
+    chal2::xor_bytes,
+    chal6::base64_to_bytes,
+    chal7::aes_ecb_decrypt,
+    ctr::{CounterLayout, Ctr},
+    modes::BlockMode,
+    padding::Pkcs7,
+};
+use set2::chal3::{random_aes_key, random_bytes};
+
+/// The challenge 7 ciphertext, ECB under "YELLOW SUBMARINE", whose plaintext
+/// the challenge encrypts again.
+const CHALLENGE_DATA: &str = include_str!("../../set1/data/chal7/7.txt");
+
+/// Returns the plaintext of the challenge 7 file.
+pub fn challenge_plaintext() -> Vec<u8> {
+    let ciphertext = base64_to_bytes(&CHALLENGE_DATA.replace('\n', "")).unwrap();
+    aes_ecb_decrypt(&ciphertext, b"YELLOW SUBMARINE", &Pkcs7).unwrap()
+}
+
+/// Encrypts with AES-CTR under a key and nonce chosen at construction, and
+/// re-encrypts edited parts of ciphertexts.
+pub struct EditService {
+    key: [u8; BLOCK_SIZE],
+    nonce: Vec<u8>,
+}
+
+impl EditService {
+    /// Creates the service with a random key and nonce.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        EditService {
+            key: random_aes_key(rng),
+            nonce: random_bytes(rng, 8),
+        }
+    }
+
+    fn ctr(&self) -> Ctr<Aes> {
+        let layout = CounterLayout::NONCE_64_COUNTER_64_LE;
+        Ctr::with_layout(Aes::new(&self.key).unwrap(), &self.nonce, layout).unwrap()
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        self.ctr().encrypt(plaintext).unwrap()
+    }
+
+    /// Returns `ciphertext` with the plaintext at `offset` replaced by
+    /// `new_text`. Returns an error if `new_text` does not fit.
+    pub fn edit(
+        &self,
+        ciphertext: &[u8],
+        offset: usize,
+        new_text: &[u8],
+    ) -> Result<Vec<u8>, &'static str> {
+        let mut edited = ciphertext.to_vec();
+        self.ctr().edit(&mut edited, offset, new_text)?;
+        Ok(edited)
+    }
+}
+
+/// This function recovers the keystream `ciphertext` was encrypted with, by
+/// editing the whole of it to zeroes.
+pub fn recover_keystream(
+    service: &EditService,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, &'static str> {
+    service.edit(ciphertext, 0, &vec![0; ciphertext.len()])
+}
+
+/// This function decrypts `ciphertext` with one call to `edit`.
+///
+/// # Examples
+/// ```
+/// use set4::chal1::{recover_plaintext, EditService};
+///
+/// let service = EditService::new(&mut rand::thread_rng());
+/// let ciphertext = service.encrypt(b"Play that funky music");
+///
+/// assert_eq!(recover_plaintext(&service, &ciphertext).unwrap(), b"Play that funky music");
+/// ```
+pub fn recover_plaintext(
+    service: &EditService,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, &'static str> {
+    let keystream = recover_keystream(service, ciphertext)?;
+    xor_bytes(ciphertext, &keystream)
+}
+
+/// This function encrypts the challenge 7 plaintext under CTR, recovers it
+/// through the edit function and prints its first line.
+pub fn random_access_read_write_ctr() {
+    let service = EditService::new(&mut rand::thread_rng());
+    let ciphertext = service.encrypt(&challenge_plaintext());
+
+    let plaintext = recover_plaintext(&service, &ciphertext).unwrap();
+    let plaintext = String::from_utf8_lossy(&plaintext);
+    println!("Decryption: {}", plaintext.lines().next().unwrap_or(""));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_edit() {
+        let mut rng = StdRng::seed_from_u64(25);
+        let service = EditService::new(&mut rng);
+        let plaintext = b"I'm back and I'm ringin' the bell";
+        let ciphertext = service.encrypt(plaintext);
+
+        let edited = service.edit(&ciphertext, 9, b"AND").unwrap();
+        assert_eq!(edited[..9], ciphertext[..9]);
+        assert_eq!(edited[12..], ciphertext[12..]);
+        assert_eq!(
+            service.encrypt(&edited),
+            b"I'm back AND I'm ringin' the bell"
+        );
+
+        assert!(service.edit(&ciphertext, 30, b"bells").is_err());
+        assert!(service.edit(&ciphertext, usize::MAX, b"b").is_err());
+    }
+
+    #[test]
+    fn test_recover_challenge_plaintext() {
+        let mut rng = StdRng::seed_from_u64(25);
+        let service = EditService::new(&mut rng);
+        let plaintext = challenge_plaintext();
+        assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+
+        let ciphertext = service.encrypt(&plaintext);
+        assert_eq!(recover_plaintext(&service, &ciphertext).unwrap(), plaintext);
+    }
+}
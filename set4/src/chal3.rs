@@ -0,0 +1,155 @@
+//! Recover the key from CBC with IV=Key
+//!
+//! The service uses its key as the IV and, when a message doesn't decrypt to
+//! ASCII, says so in an error holding the decrypted message. Sending the
+//! first ciphertext block `C1`, a zero block and `C1` again gives
+//! `P1 = D(C1) ^ IV` and `P3 = D(C1) ^ 0`, so `P1 ^ P3` is the IV, which is
+//! the key.
+
+use rand::Rng;
+use set1::{aes::BLOCK_SIZE, chal2::xor_bytes};
+use set2::{
+    chal2::{aes_cbc_decrypt, aes_cbc_encrypt},
+    chal3::random_aes_key,
+};
+use std::fmt;
+
+/// Why the service refused a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReceiveError {
+    /// The ciphertext has the wrong length or bad padding.
+    Decryption(&'static str),
+    /// The message has bytes above 127. The decrypted message is echoed
+    /// back, which is the leak.
+    NotAscii(Vec<u8>),
+}
+
+impl fmt::Display for ReceiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReceiveError::Decryption(error) => write!(f, "{}", error),
+            ReceiveError::NotAscii(plaintext) => {
+                write!(f, "Message is not ASCII: {:02x?}", plaintext)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReceiveError {}
+
+/// Encrypts and receives messages with AES-128-CBC, using the key as IV.
+pub struct KeyAsIvService {
+    key: [u8; BLOCK_SIZE],
+}
+
+impl KeyAsIvService {
+    /// Creates the service with a random key.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        KeyAsIvService {
+            key: random_aes_key(rng),
+        }
+    }
+
+    /// Encrypts a message, as the sender would.
+    pub fn encrypt(&self, message: &[u8]) -> Vec<u8> {
+        aes_cbc_encrypt(message, &self.key, &self.key).unwrap()
+    }
+
+    /// Decrypts a message and checks it is ASCII, as the receiver would.
+    pub fn receive(&self, ciphertext: &[u8]) -> Result<Vec<u8>, ReceiveError> {
+        let plaintext =
+            aes_cbc_decrypt(ciphertext, &self.key, &self.key).map_err(ReceiveError::Decryption)?;
+
+        if !plaintext.is_ascii() {
+            return Err(ReceiveError::NotAscii(plaintext));
+        }
+        Ok(plaintext)
+    }
+
+    /// Returns whether `key` is the key of the service.
+    pub fn check_key(&self, key: &[u8]) -> bool {
+        key == self.key
+    }
+}
+
+/// This function recovers the key of the service. It encrypts three blocks
+/// and sends `C1 || 0 || C1 || C2 || C3`: the original `C2` and `C3` still
+/// follow `C1`, so the message ends with the original last block and its
+/// padding stays valid. The first three blocks decrypt to bytes above 127
+/// almost surely, and the error gives them back.
+///
+/// # Examples
+/// ```
+/// use set4::chal3::{recover_key, KeyAsIvService};
+///
+/// let service = KeyAsIvService::new(&mut rand::thread_rng());
+/// let key = recover_key(&service).unwrap();
+///
+/// assert!(service.check_key(&key));
+/// ```
+pub fn recover_key(service: &KeyAsIvService) -> Result<Vec<u8>, &'static str> {
+    let ciphertext = service.encrypt(&[b'A'; 3 * BLOCK_SIZE]);
+    let first = &ciphertext[..BLOCK_SIZE];
+
+    let mut modified = first.to_vec();
+    modified.extend_from_slice(&[0; BLOCK_SIZE]);
+    modified.extend_from_slice(first);
+    modified.extend_from_slice(&ciphertext[BLOCK_SIZE..]);
+
+    match service.receive(&modified) {
+        Err(ReceiveError::NotAscii(plaintext)) => xor_bytes(
+            &plaintext[..BLOCK_SIZE],
+            &plaintext[2 * BLOCK_SIZE..3 * BLOCK_SIZE],
+        ),
+        Err(ReceiveError::Decryption(error)) => Err(error),
+        Ok(_) => Err("The modified message decrypted to ASCII"),
+    }
+}
+
+/// This function recovers the key of the service and prints it.
+pub fn cbc_key_as_iv() {
+    let service = KeyAsIvService::new(&mut rand::thread_rng());
+    let key = recover_key(&service).unwrap();
+
+    println!(
+        "Recovered key: {:02x?}, correct: {}",
+        key,
+        service.check_key(&key)
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_receive() {
+        let mut rng = StdRng::seed_from_u64(27);
+        let service = KeyAsIvService::new(&mut rng);
+
+        let ciphertext = service.encrypt(b"Ice Ice Baby");
+        assert_eq!(service.receive(&ciphertext).unwrap(), b"Ice Ice Baby");
+
+        let ciphertext = service.encrypt(b"\xffIce Ice Baby");
+        assert!(matches!(
+            service.receive(&ciphertext),
+            Err(ReceiveError::NotAscii(_))
+        ));
+        assert!(matches!(
+            service.receive(&ciphertext[..8]),
+            Err(ReceiveError::Decryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_recover_key() {
+        let mut rng = StdRng::seed_from_u64(27);
+
+        for _ in 0..20 {
+            let service = KeyAsIvService::new(&mut rng);
+            let key = recover_key(&service).unwrap();
+            assert!(service.check_key(&key));
+        }
+    }
+}
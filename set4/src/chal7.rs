@@ -0,0 +1,184 @@
+//! HMAC-SHA1 with an artificial timing leak
+//!
+//! The server checks the HMAC of a file name against the signature in the
+//! request, one byte at a time, sleeping after each byte and stopping at the
+//! first one that differs. How long a request takes tells how many leading
+//! bytes of the signature are right.
+
+use rand::Rng;
+use set1::{chal1::hex_to_bytes, chal5::bytes_to_hex, hmac::hmac_sha1, sha1::DIGEST_SIZE};
+use set2::{
+    chal3::random_bytes,
+    chal5::parse_kv,
+    oracle::Oracle,
+    transport::{serve_http, HttpOracle, HttpRequest},
+};
+use std::{io, net::TcpListener, thread, time::Duration};
+
+/// This function compares two byte strings the slow way: byte by byte,
+/// sleeping `delay` after every byte and returning at the first difference.
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+    true
+}
+
+/// Checks HMAC-SHA1 signatures of file names under a key chosen at
+/// construction, with `insecure_compare`.
+pub struct HmacServer {
+    key: Vec<u8>,
+    delay: Duration,
+}
+
+impl HmacServer {
+    /// Creates the server with a random 16 byte key.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, delay: Duration) -> Self {
+        HmacServer {
+            key: random_bytes(rng, 16),
+            delay,
+        }
+    }
+
+    /// Returns whether `signature` is the HMAC of `file`.
+    pub fn verify(&self, file: &[u8], signature: &[u8]) -> bool {
+        insecure_compare(&hmac_sha1(&self.key, file), signature, self.delay)
+    }
+
+    /// Answers `POST /test` with a `file=...&signature=<hex>` body: 200 if the
+    /// signature is valid, 500 if not, 400 for a malformed body and 404 for
+    /// anything else.
+    pub fn handle(&self, request: &HttpRequest) -> (u16, Vec<u8>) {
+        if request.method != "POST" || request.path != "/test" {
+            return (404, b"Not found".to_vec());
+        }
+
+        let fields = match std::str::from_utf8(&request.body).map(parse_kv) {
+            Ok(Ok(fields)) => fields,
+            _ => return (400, b"Body should be file=...&signature=...".to_vec()),
+        };
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let (file, signature) = match (field("file"), field("signature").map(hex_to_bytes)) {
+            (Some(file), Some(Ok(signature))) if signature.len() == DIGEST_SIZE => {
+                (file, signature)
+            }
+            _ => return (400, b"Body should be file=...&signature=...".to_vec()),
+        };
+
+        if self.verify(file.as_bytes(), &signature) {
+            (200, b"OK".to_vec())
+        } else {
+            (500, b"Invalid signature".to_vec())
+        }
+    }
+
+    /// Serves `handle` over HTTP on `listener` until it fails.
+    pub fn serve(self, listener: TcpListener) -> io::Result<()> {
+        serve_http(listener, move |request| self.handle(request))
+    }
+}
+
+/// This function starts the server on a free port, sends it a good and a
+/// bad signature and prints the answers with their timings.
+pub fn hmac_timing_leak() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = HmacServer::new(&mut rand::thread_rng(), Duration::from_millis(5));
+    let signature = hmac_sha1(&server.key, b"foo");
+    thread::spawn(move || server.serve(listener));
+
+    let mut oracle = HttpOracle::new(addr, "/test").unwrap();
+    for signature in [signature, [0; DIGEST_SIZE]] {
+        let body = format!("file=foo&signature={}", bytes_to_hex(&signature));
+        let response = oracle.query(body.as_bytes()).unwrap();
+        println!("{} after {:?}", response.status, response.elapsed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Instant;
+
+    fn request(body: &str) -> HttpRequest {
+        HttpRequest {
+            method: "POST".to_string(),
+            path: "/test".to_string(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let server = HmacServer::new(&mut rng, Duration::ZERO);
+        let signature = bytes_to_hex(&hmac_sha1(&server.key, b"foo"));
+
+        let body = format!("file=foo&signature={}", signature);
+        assert_eq!(server.handle(&request(&body)).0, 200);
+        let body = format!("file=bar&signature={}", signature);
+        assert_eq!(server.handle(&request(&body)).0, 500);
+
+        assert_eq!(server.handle(&request("file=foo")).0, 400);
+        assert_eq!(server.handle(&request("file=foo&signature=00")).0, 400);
+        assert_eq!(server.handle(&request("nonsense")).0, 400);
+
+        let mut get = request(&body);
+        get.method = "GET".to_string();
+        assert_eq!(server.handle(&get).0, 404);
+    }
+
+    #[test]
+    fn test_timing_leak() {
+        let delay = Duration::from_millis(50);
+        let mut rng = StdRng::seed_from_u64(31);
+        let server = HmacServer::new(&mut rng, delay);
+        let mac = hmac_sha1(&server.key, b"foo");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || server.serve(listener));
+        let mut oracle = HttpOracle::new(addr, "/test").unwrap();
+
+        // a signature with the first three bytes right takes at least three
+        // delays, one with the first byte wrong answers right away.
+        let mut signature = [0u8; DIGEST_SIZE];
+        signature[..3].copy_from_slice(&mac[..3]);
+        signature[3] = !mac[3];
+        let body = format!("file=foo&signature={}", bytes_to_hex(&signature));
+        let slow = oracle.query(body.as_bytes()).unwrap();
+        assert_eq!(slow.status, 500);
+        assert!(slow.elapsed >= 3 * delay);
+
+        // the two requests are compared with each other rather than with the
+        // clock, so a delay of slack covers any overhead of the fast one.
+        signature[0] = !mac[0];
+        let body = format!("file=foo&signature={}", bytes_to_hex(&signature));
+        let fast = oracle.query(body.as_bytes()).unwrap();
+        assert_eq!(fast.status, 500);
+        assert!(slow.elapsed.saturating_sub(fast.elapsed) >= 2 * delay);
+    }
+
+    #[test]
+    fn test_insecure_compare() {
+        let start = Instant::now();
+        assert!(insecure_compare(b"abc", b"abc", Duration::from_millis(1)));
+        assert!(start.elapsed() >= Duration::from_millis(3));
+
+        assert!(!insecure_compare(b"abc", b"abd", Duration::ZERO));
+        assert!(!insecure_compare(b"abc", b"ab", Duration::ZERO));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod chal1;
+pub mod chal3;
+pub mod chal7;
@@ -0,0 +1,7 @@
+use set4::{chal1::random_access_read_write_ctr, chal3::cbc_key_as_iv, chal7::hmac_timing_leak};
+
+fn main() {
+    random_access_read_write_ctr();
+    cbc_key_as_iv();
+    hmac_timing_leak();
+}